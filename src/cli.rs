@@ -1,13 +1,13 @@
 use crate::config::Config;
 use crate::interactive;
-use crate::models::{BundleMode, NoteFormat};
-use crate::parser::{self, ParseOptions};
+use crate::models::{BundleMode, FormatSelection};
+use crate::pipeline::{Pipeline, PipelineOptions};
 use crate::render::{self, OutputFormat};
 use crate::reports::BatchReport;
 use crate::samples;
 use crate::selftest;
 use crate::util;
-use crate::validate::{self, Severity, Template, ValidationIssue};
+use crate::validate::{self, RuleSet, Template, ValidationIssue, ValidationReport};
 use anyhow::{anyhow, Result};
 use clap::{Args, Parser, Subcommand};
 use glob::glob;
@@ -43,8 +43,8 @@ pub enum Commands {
 pub struct ParseArgs {
     #[arg(long)]
     pub input: PathBuf,
-    #[arg(long, value_enum)]
-    pub format: NoteFormat,
+    #[arg(long)]
+    pub format: FormatSelection,
     #[arg(long)]
     pub out: PathBuf,
     #[arg(long, value_enum)]
@@ -55,6 +55,8 @@ pub struct ParseArgs {
     pub bundle: Option<BundleMode>,
     #[arg(long)]
     pub interactive: bool,
+    #[arg(long)]
+    pub reproducible: bool,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -63,8 +65,8 @@ pub struct BatchArgs {
     pub input_dir: PathBuf,
     #[arg(long)]
     pub glob: Option<String>,
-    #[arg(long, value_enum)]
-    pub format: NoteFormat,
+    #[arg(long)]
+    pub format: FormatSelection,
     #[arg(long)]
     pub out_dir: PathBuf,
     #[arg(long, value_enum)]
@@ -73,6 +75,8 @@ pub struct BatchArgs {
     pub config: Option<PathBuf>,
     #[arg(long, value_enum)]
     pub bundle: Option<BundleMode>,
+    #[arg(long)]
+    pub reproducible: bool,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -93,14 +97,16 @@ pub struct SampleArgs {
 pub struct ValidateArgs {
     #[arg(value_name = "INPUT")]
     pub input: Option<PathBuf>,
-    #[arg(long, value_enum)]
-    pub template: Option<Template>,
+    #[arg(long)]
+    pub template: Option<FormatSelection>,
     #[arg(long)]
     pub strict: bool,
     #[arg(long)]
     pub json: bool,
     #[arg(long)]
     pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub reproducible: bool,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -111,10 +117,12 @@ pub struct ValidateArgs {
 pub struct PreviewArgs {
     #[arg(value_name = "INPUT")]
     pub input: PathBuf,
-    #[arg(long, value_enum)]
-    pub template: Option<Template>,
+    #[arg(long)]
+    pub template: Option<FormatSelection>,
     #[arg(long)]
     pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub reproducible: bool,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -135,6 +143,8 @@ pub struct InitArgs {
 pub struct DemoArgs {
     #[arg(long, default_value = "demo_outputs")]
     pub out_dir: PathBuf,
+    #[arg(long)]
+    pub reproducible: bool,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -145,7 +155,7 @@ pub struct DemoArgs {
 pub struct SelftestArgs {
     #[arg(long)]
     pub fixtures: String,
-    #[arg(long, value_enum)]
+    #[arg(long)]
     pub template: Option<Template>,
     #[arg(long)]
     pub strict: bool,
@@ -153,6 +163,10 @@ pub struct SelftestArgs {
     pub json: bool,
     #[arg(long)]
     pub out: Option<PathBuf>,
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub reproducible: bool,
 }
 
 pub fn run() -> Result<()> {
@@ -171,44 +185,37 @@ pub fn run() -> Result<()> {
 
 fn run_parse(args: &ParseArgs) -> Result<()> {
     let config = Config::load(args.config.as_deref())?;
-    let input = util::read_to_string(&args.input)?;
-    let bundle_mode = args.bundle.unwrap_or(config.bundle.mode_default);
-    let (note_texts, bundle_warnings) = parser::split_bundle(&input, bundle_mode, &config);
-
-    let apply_heuristics = if args.interactive {
-        interactive::prompt_apply_heuristics()?
-    } else {
-        config.enable_fallback_heuristics
-    };
+    config.ensure_selection(&args.format)?;
+    let mut options = PipelineOptions::from_config(&config, args.format.clone());
+    if let Some(bundle) = args.bundle {
+        options.bundle_mode = bundle;
+    }
+    options.reproducible |= args.reproducible;
+    if args.interactive {
+        options.apply_heuristics = interactive::prompt_apply_heuristics()?;
+    }
 
-    let mut notes = Vec::new();
-    for (idx, note_text) in note_texts.iter().enumerate() {
-        let (candidates, mut warnings) = parser::extract_candidates(
-            note_text,
-            args.format,
-            &config,
-            ParseOptions { apply_heuristics },
-        );
-        warnings.extend(bundle_warnings.clone());
-
-        let selected = if args.interactive {
-            interactive::review_sections(&candidates)?
-        } else {
-            candidates
-        };
-
-        let note = parser::build_note(
-            selected,
-            args.format,
-            Some(args.input.display().to_string()),
-            idx + 1,
-            warnings,
-        );
-        notes.push(note);
+    let mut pipeline = Pipeline::new(config, options);
+    if args.interactive {
+        pipeline = pipeline.with_hooks(interactive::InteractiveReviewer);
     }
+    let output = pipeline.parse_file(&args.input)?;
 
-    let rendered = render::render_notes(&notes, args.out_format, config.csv.layout)?;
+    let rendered =
+        render::render_notes(&output.notes, args.out_format, pipeline.config().csv.layout)?;
     util::write_string(&args.out, &rendered)?;
+    if args.out_format == OutputFormat::Csv {
+        write_tables(&args.out, &output.notes)?;
+    }
+    Ok(())
+}
+
+fn write_tables(out_path: &Path, notes: &[crate::models::StructuredNote]) -> Result<()> {
+    let stem = util::file_stem(out_path);
+    for (name, table) in render::render_tables(notes)? {
+        let table_path = out_path.with_file_name(format!("{}.{}.csv", stem, name));
+        util::write_string(&table_path, &table)?;
+    }
     Ok(())
 }
 
@@ -217,6 +224,9 @@ fn run_batch_command(args: &BatchArgs) -> Result<()> {
     let report = run_batch(args, &config)?;
     let report_path = args.out_dir.join("batch_report.json");
     report.write_to(&report_path)?;
+    if args.out_format == OutputFormat::Csv {
+        util::write_string(&args.out_dir.join("batch_report.csv"), &report.to_csv()?)?;
+    }
     Ok(())
 }
 
@@ -235,12 +245,18 @@ pub fn run_batch(args: &BatchArgs, config: &Config) -> Result<BatchReport> {
         .ok_or_else(|| anyhow!("Invalid glob pattern"))?
         .to_string();
 
-    let bundle_mode = args.bundle.unwrap_or(config.bundle.mode_default);
+    config.ensure_selection(&args.format)?;
+    let mut options = PipelineOptions::from_config(config, args.format.clone());
+    if let Some(bundle) = args.bundle {
+        options.bundle_mode = bundle;
+    }
+    options.reproducible |= args.reproducible;
+    let pipeline = Pipeline::new(config.clone(), options);
 
     for entry in glob(&pattern_str)? {
         match entry {
             Ok(path) => {
-                let file_result = process_file(&path, args, config, bundle_mode);
+                let file_result = process_file(&path, args, &pipeline);
                 match file_result {
                     Ok(notes) => {
                         report.record_ok(&notes);
@@ -257,45 +273,29 @@ pub fn run_batch(args: &BatchArgs, config: &Config) -> Result<BatchReport> {
     }
 
     report.finalize();
-    report.runtime_ms = start.elapsed().as_millis();
+    if !pipeline.options().reproducible {
+        report.runtime_ms = start.elapsed().as_millis();
+    }
     Ok(report)
 }
 
 fn process_file(
     path: &Path,
     args: &BatchArgs,
-    config: &Config,
-    bundle_mode: BundleMode,
+    pipeline: &Pipeline,
 ) -> Result<Vec<crate::models::StructuredNote>> {
-    let content = util::read_to_string(path)?;
-    let (note_texts, bundle_warnings) = parser::split_bundle(&content, bundle_mode, config);
-    let mut notes = Vec::new();
-    for (idx, note_text) in note_texts.iter().enumerate() {
-        let (candidates, mut warnings) = parser::extract_candidates(
-            note_text,
-            args.format,
-            config,
-            ParseOptions {
-                apply_heuristics: config.enable_fallback_heuristics,
-            },
-        );
-        warnings.extend(bundle_warnings.clone());
-        let note = parser::build_note(
-            candidates,
-            args.format,
-            Some(path.display().to_string()),
-            idx + 1,
-            warnings,
-        );
-        notes.push(note);
-    }
+    let output = pipeline.parse_file(path)?;
+    let notes = output.notes;
 
-    let rendered = render::render_notes(&notes, args.out_format, config.csv.layout)?;
+    let rendered = render::render_notes(&notes, args.out_format, pipeline.config().csv.layout)?;
     let stem = util::file_stem(path);
     let out_path = args
         .out_dir
         .join(format!("{}.{}", stem, args.out_format.extension()));
     util::write_string(&out_path, &rendered)?;
+    if args.out_format == OutputFormat::Csv {
+        write_tables(&out_path, &notes)?;
+    }
     Ok(notes)
 }
 
@@ -305,51 +305,28 @@ fn run_sample(args: &SampleArgs) -> Result<()> {
 
 fn run_validate(args: &ValidateArgs) -> Result<()> {
     if let Some(input) = &args.input {
-        let template = args.template.unwrap_or(Template::Soap);
+        let template = args
+            .template
+            .clone()
+            .unwrap_or(FormatSelection::Fixed(Template::Soap));
         let config = Config::load(args.config.as_deref())?;
-        let input_text = util::read_to_string(input)?;
-        let (note_texts, bundle_warnings) =
-            parser::split_bundle(&input_text, config.bundle.mode_default, &config);
-        let mut reports = Vec::new();
-        let mut has_error = false;
-
-        for (idx, note_text) in note_texts.iter().enumerate() {
-            let (candidates, mut warnings) = parser::extract_candidates(
-                note_text,
-                template_to_format(template),
-                &config,
-                ParseOptions {
-                    apply_heuristics: config.enable_fallback_heuristics,
-                },
-            );
-            warnings.extend(bundle_warnings.clone());
-            let note = parser::build_note(
-                candidates,
-                template_to_format(template),
-                Some(input.display().to_string()),
-                idx + 1,
-                warnings,
-            );
-            let issues = validate::validate_note(&note, template, args.strict);
-            if issues.iter().any(|i| i.severity == Severity::Error) {
-                has_error = true;
-            }
-            reports.push(ValidationReport {
-                note_index: idx + 1,
-                issues,
-            });
-        }
+        config.ensure_selection(&template)?;
+        let mut options = PipelineOptions::from_config(&config, template.clone());
+        options.strict = args.strict;
+        options.reproducible |= args.reproducible;
+        let output = Pipeline::new(config, options).parse_file(input)?;
+        let has_error = output.has_errors();
 
         if args.json {
             let payload = ValidationSummary {
                 input: input.display().to_string(),
                 template,
                 strict: args.strict,
-                reports,
+                reports: output.reports,
             };
             println!("{}", serde_json::to_string_pretty(&payload)?);
         } else {
-            print_validation_text(&reports);
+            print_validation_text(&input.display().to_string(), &output.reports);
         }
 
         if has_error {
@@ -361,6 +338,9 @@ fn run_validate(args: &ValidateArgs) -> Result<()> {
     if let Some(config_path) = &args.config {
         let config = Config::load(Some(config_path))?;
         println!("{}", config.summary());
+        for format in config.formats.names() {
+            println!("{}", RuleSet::resolve(&config, &format)?.describe());
+        }
         return Ok(());
     }
 
@@ -371,35 +351,30 @@ fn run_validate(args: &ValidateArgs) -> Result<()> {
 
 fn run_preview(args: &PreviewArgs) -> Result<()> {
     let config = Config::load(args.config.as_deref())?;
-    let template = args.template.unwrap_or(Template::Soap);
-    let input_text = util::read_to_string(&args.input)?;
-    let (note_texts, _warnings) =
-        parser::split_bundle(&input_text, config.bundle.mode_default, &config);
-
-    for (idx, note_text) in note_texts.iter().enumerate() {
-        let (candidates, _) = parser::extract_candidates(
-            note_text,
-            template_to_format(template),
-            &config,
-            ParseOptions {
-                apply_heuristics: config.enable_fallback_heuristics,
-            },
-        );
-        let note = parser::build_note(
-            candidates,
-            template_to_format(template),
-            Some(args.input.display().to_string()),
-            idx + 1,
-            Vec::new(),
-        );
-        println!("Note {}:", idx + 1);
-        for summary in validate::summarize_sections(&note) {
+    let template = args
+        .template
+        .clone()
+        .unwrap_or(FormatSelection::Fixed(Template::Soap));
+    config.ensure_selection(&template)?;
+    let mut options = PipelineOptions::from_config(&config, template);
+    options.reproducible |= args.reproducible;
+    let output = Pipeline::new(config, options).parse_file(&args.input)?;
+
+    for (idx, note) in output.notes.iter().enumerate() {
+        match &note.metadata.format_detection {
+            Some(detection) => println!(
+                "Note {} (detected {}, score {:.2}, margin {:.2}):",
+                note.note_index, detection.format, detection.score, detection.margin
+            ),
+            None => println!("Note {}:", note.note_index),
+        }
+        for summary in validate::summarize_sections(note) {
             println!(
                 "- {}: {} lines, {} chars",
                 summary.name, summary.line_count, summary.char_count
             );
         }
-        if idx + 1 < note_texts.len() {
+        if idx + 1 < output.notes.len() {
             println!();
         }
     }
@@ -431,35 +406,18 @@ fn run_demo(args: &DemoArgs) -> Result<()> {
         .ok_or_else(|| anyhow!("Invalid demo glob pattern"))?
         .to_string();
 
+    let mut options = PipelineOptions::from_config(&config, FormatSelection::Auto);
+    options.reproducible |= args.reproducible;
+    let pipeline = Pipeline::new(config.clone(), options);
     for entry in glob(&pattern_str)? {
         let path = entry?;
-        let content = util::read_to_string(&path)?;
-        let format = match path.file_name().and_then(|s| s.to_str()) {
-            Some(name) if name.contains("_1") || name.contains("_4") => Template::Soap,
-            Some(name) if name.contains("_2") || name.contains("_5") => Template::Hp,
-            _ => Template::Discharge,
-        };
-        let (candidates, _) = parser::extract_candidates(
-            &content,
-            template_to_format(format),
-            &config,
-            ParseOptions {
-                apply_heuristics: config.enable_fallback_heuristics,
-            },
-        );
-        let note = parser::build_note(
-            candidates,
-            template_to_format(format),
-            Some(path.display().to_string()),
-            1,
-            Vec::new(),
-        );
-        let rendered =
-            render::render_notes(&[note.clone()], OutputFormat::Json, config.csv.layout)?;
+        let output = pipeline.parse_file(&path)?;
+
+        let rendered = render::render_notes(&output.notes, OutputFormat::Json, config.csv.layout)?;
         let out_path = outputs_dir.join(format!("{}.json", util::file_stem(&path)));
         util::write_string(&out_path, &rendered)?;
 
-        let issues = validate::validate_note(&note, format, false);
+        let issues: Vec<&ValidationIssue> = output.issues().collect();
         let report_path = outputs_dir.join(format!("{}.validation.json", util::file_stem(&path)));
         util::write_string(&report_path, &serde_json::to_string_pretty(&issues)?)?;
     }
@@ -469,9 +427,20 @@ fn run_demo(args: &DemoArgs) -> Result<()> {
 }
 
 fn run_selftest(args: &SelftestArgs) -> Result<()> {
-    let template = args.template.unwrap_or(Template::Soap);
+    let template = args.template.clone().unwrap_or(Template::Soap);
     let out_dir = args.out.as_deref();
-    let summary = selftest::run_selftest(&args.fixtures, template, args.strict, out_dir)?;
+    let config = match &args.config {
+        Some(path) => Config::load(Some(path))?,
+        None => Config::default(),
+    };
+    let summary = selftest::run_selftest_with_config(
+        &args.fixtures,
+        template,
+        args.strict,
+        args.reproducible,
+        out_dir,
+        config,
+    )?;
 
     if args.json {
         println!("{}", serde_json::to_string_pretty(&summary)?);
@@ -488,29 +457,15 @@ fn run_selftest(args: &SelftestArgs) -> Result<()> {
     Ok(())
 }
 
-fn template_to_format(template: Template) -> NoteFormat {
-    match template {
-        Template::Soap => NoteFormat::Soap,
-        Template::Hp => NoteFormat::Hp,
-        Template::Discharge => NoteFormat::Discharge,
-    }
-}
-
-#[derive(Debug, serde::Serialize)]
-struct ValidationReport {
-    note_index: usize,
-    issues: Vec<ValidationIssue>,
-}
-
 #[derive(Debug, serde::Serialize)]
 struct ValidationSummary {
     input: String,
-    template: Template,
+    template: FormatSelection,
     strict: bool,
     reports: Vec<ValidationReport>,
 }
 
-fn print_validation_text(reports: &[ValidationReport]) {
+fn print_validation_text(input: &str, reports: &[ValidationReport]) {
     for report in reports {
         println!("Note {}:", report.note_index);
         if report.issues.is_empty() {
@@ -523,7 +478,15 @@ fn print_validation_text(reports: &[ValidationReport]) {
                 .as_ref()
                 .map(|s| format!(" [{}]", s))
                 .unwrap_or_default();
-            println!("  - {:?}: {}{}", issue.severity, issue.message, section);
+            let location = issue
+                .span
+                .as_ref()
+                .map(|span| format!(" ({}:{})", input, span.line_start))
+                .unwrap_or_default();
+            println!(
+                "  - {:?}: {}{}{}",
+                issue.severity, issue.message, section, location
+            );
         }
     }
 }
@@ -532,6 +495,33 @@ fn default_config_template() -> String {
     let template = r#"# StructaMed config template
 # Customize section orders, aliases, and bundle delimiters.
 
+# Map variants to canonical headings.
+heading_aliases = { "Hx" = "PMH", "Dx" = "Assessment" }
+
+# Enable heuristic fallbacks for missing headings.
+enable_fallback_heuristics = true
+
+# Run fallback heuristics line by line even when regular headings exist.
+fallback_per_line = true
+
+# Headings outside the target format: "narrative" (merge), "keep", or "drop".
+unmapped_sections = "narrative"
+
+glob_default = "*.txt"
+
+# "A/P:" sections: "split" into Assessment and Plan, or keep them "combined".
+assessment_plan = "split"
+
+# Decorated heading styles to recognize besides CAPS and "Heading:" lines.
+heading_styles = ["markdown", "bold", "numbered", "slash", "dashed", "underlined"]
+
+# Match misspelled headings by edit distance (off in strict mode unless strict = true).
+# [fuzzy_headings]
+# enabled = true
+# max_distance = 2
+# min_similarity = 0.8
+# strict = false
+
 [formats.soap]
 section_order = ["Subjective", "Objective", "Assessment", "Plan"]
 
@@ -559,20 +549,84 @@ section_order = [
   "Instructions"
 ]
 
-# Map variants to canonical headings.
-heading_aliases = { "Hx" = "PMH", "Dx" = "Assessment" }
-
-# Enable heuristic fallbacks for missing headings.
-enable_fallback_heuristics = true
+# Declare additional formats under [formats.<name>] and pass the name to
+# --format/--template.
+# [formats.progress]
+# section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+# required_sections = ["Assessment", "Plan"]
+
+# Lab extraction: sections to scan and analyte dictionary overrides.
+# [labs]
+# sections = ["Objective", "Hospital Course"]
+# [labs.analytes.Ferritin]
+# synonyms = ["Ferr"]
+# unit = "ng/mL"
+# low = 30
+# high = 400
+# [labs.analytes.Calcium]
+# enabled = false
+
+# Declare extra section names (with aliases, label, and optional code).
+# [sections."Vital Signs"]
+# aliases = ["Vitals", "VS"]
+# label = "Vital signs"
+
+# Tune validation per format; unset keys keep the built-in rules.
+# [validation.soap]
+# required = [{ section = "Subjective", aliases = ["S"] }, { section = "Plan", aliases = ["P"] }]
+# optional = ["Narrative"]
+# min_length = 20
+# min_lengths = { "Plan" = 10 }
+# max_lengths = { "Subjective" = 4000 }
+# severity = { duplicate_section = "error", unknown_section = "warn" }
+#
+# Content rules check section text with regexes or keyword lists.
+# [[validation.soap.rules]]
+# code = "plan_follow_up"
+# section = "Plan"
+# must_match = '(?i)follow[- ]?up\s+(in\s+)?\d+\s*(day|week|month)s?'
+# message = "Plan should state a follow-up interval"
+#
+# [[validation.soap.rules]]
+# code = "assessment_placeholder"
+# section = "Assessment"
+# must_not_match = '\*\*\*'
+# severity = "error"
+#
+# H&P notes warn when ROS covers fewer systems than this (0 disables).
+# [validation.hp]
+# ros_min_systems = 2
+
+# ROS negation triggers (NegEx-style) and systems; unset lists keep the defaults.
+# [ros]
+# negation = ["denies", "negative for", "no", "without"]
+# post_negation = ["denied", "negative", "absent"]
+# uncertainty = ["possible", "questionable", "?"]
+# terminators = ["but", "however", "except", "reports"]
+# [ros.systems."Sleep"]
+# aliases = ["Sleep Hx"]
+# findings = ["snoring", "insomnia"]
+
+# Key/value lines before the first heading become the note header.
+# Built-in fields: patient_name, dob, mrn, encounter_date, provider, location.
+# Override a field's keys, add new fields, or disable one with an empty list.
+# [header.fields]
+# mrn = ["MRN", "Chart #"]
+# room = ["Room", "Bed"]
+# location = []
 
 [bundle]
+# auto: delimiters, then dates, then heading resets; or on/off/delimiters/dates/headings.
 mode_default = "auto"
 delimiters = ["----- NOTE -----", "=== VISIT ==="]
+# Regex delimiters; named captures (e.g. date, author, note_type) are stored
+# in each note's metadata.delimiter_captures.
+# delimiter_patterns = ['^Note ID: (?P<note_id>\d+) \| Author: (?P<author>[^|]+) \| (?P<date>\d{4}-\d{2}-\d{2} \d{2}:\d{2})$']
+# Keep the delimiter line at the top of each note ("keep") or drop it ("drop").
+# delimiter_line = "drop"
 
 [csv]
 layout = "wide"
-
-glob_default = "*.txt"
 "#;
     template.to_string()
 }
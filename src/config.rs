@@ -1,42 +1,697 @@
-use crate::models::{BundleMode, CsvLayout, NoteFormat, SectionName};
+use crate::models::{
+    BundleMode, CsvLayout, FormatSelection, HeadingStyle, NoteFormat, SectionName,
+};
+use crate::sections::SectionRegistry;
 use crate::util;
+use crate::validate::{Severity, ISSUE_CODES};
 use anyhow::{anyhow, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub formats: FormatsConfig,
     #[serde(default)]
-    pub heading_aliases: HashMap<String, String>,
+    pub sections: BTreeMap<String, SectionDef>,
+    #[serde(default)]
+    pub heading_aliases: BTreeMap<String, String>,
     #[serde(default = "default_true")]
     pub enable_fallback_heuristics: bool,
+    #[serde(default = "default_true")]
+    pub fallback_per_line: bool,
+    #[serde(default)]
+    pub unmapped_sections: UnmappedPolicy,
+    #[serde(default)]
+    pub assessment_plan: AssessmentPlanMode,
+    #[serde(default)]
+    pub fuzzy_headings: FuzzyHeadingConfig,
+    #[serde(default)]
+    pub labs: LabsConfig,
+    #[serde(default)]
+    pub ros: RosConfig,
+    #[serde(default = "HeadingStyle::decorated")]
+    pub heading_styles: Vec<HeadingStyle>,
+    #[serde(default)]
+    pub validation: BTreeMap<String, ValidationSpec>,
     #[serde(default)]
     pub bundle: BundleConfig,
     #[serde(default)]
     pub csv: CsvConfig,
+    #[serde(default)]
+    pub header: HeaderConfig,
     #[serde(default = "default_glob")]
     pub glob_default: String,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SectionDef {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FormatsConfig {
+    #[serde(default = "FormatSpec::soap")]
     pub soap: FormatSpec,
+    #[serde(default = "FormatSpec::hp")]
     pub hp: FormatSpec,
+    #[serde(default = "FormatSpec::discharge")]
     pub discharge: FormatSpec,
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, FormatSpec>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FormatSpec {
     pub section_order: Vec<SectionName>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_sections: Vec<SectionName>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required: Option<Vec<RequiredGroup>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optional: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub min_lengths: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub max_lengths: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity: BTreeMap<String, Severity>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<ContentRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ros_min_systems: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequiredGroup {
+    pub section: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+impl RequiredGroup {
+    fn new(section: &str, aliases: &[&str]) -> Self {
+        Self {
+            section: section.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.section.as_str()).chain(self.aliases.iter().map(|a| a.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentRule {
+    pub code: String,
+    pub section: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default = "default_rule_severity")]
+    pub severity: Severity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub must_match: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub must_not_match: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any_of: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub none_of: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ContentCheck {
+    Require(Regex),
+    Forbid(Regex),
+}
+
+impl ContentRule {
+    pub fn checks(&self) -> std::result::Result<Vec<ContentCheck>, regex::Error> {
+        let mut checks = Vec::new();
+        if let Some(pattern) = &self.must_match {
+            checks.push(ContentCheck::Require(Regex::new(pattern)?));
+        }
+        if !self.any_of.is_empty() {
+            checks.push(ContentCheck::Require(keyword_regex(&self.any_of)?));
+        }
+        if let Some(pattern) = &self.must_not_match {
+            checks.push(ContentCheck::Forbid(Regex::new(pattern)?));
+        }
+        if !self.none_of.is_empty() {
+            checks.push(ContentCheck::Forbid(keyword_regex(&self.none_of)?));
+        }
+        Ok(checks)
+    }
+}
+
+fn keyword_regex(keywords: &[String]) -> std::result::Result<Regex, regex::Error> {
+    let alternatives: Vec<String> = keywords.iter().map(|k| regex::escape(k.trim())).collect();
+    Regex::new(&format!(
+        r"(?i)(?:^|\W)(?:{})(?:$|\W)",
+        alternatives.join("|")
+    ))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BundleConfig {
     pub mode_default: BundleMode,
     pub delimiters: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delimiter_patterns: Vec<String>,
+    #[serde(default)]
+    pub delimiter_line: DelimiterLine,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnmappedPolicy {
+    #[default]
+    Narrative,
+    Keep,
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssessmentPlanMode {
+    #[default]
+    Split,
+    Combined,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzyHeadingConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_fuzzy_distance")]
+    pub max_distance: usize,
+    #[serde(default = "default_fuzzy_similarity")]
+    pub min_similarity: f64,
+    #[serde(default)]
+    pub strict: bool,
+}
+
+impl FuzzyHeadingConfig {
+    pub fn active(&self, strict: bool) -> bool {
+        self.enabled && (!strict || self.strict)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabsConfig {
+    #[serde(default = "default_lab_sections")]
+    pub sections: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub analytes: BTreeMap<String, LabAnalyte>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LabAnalyte {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub synonyms: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<f64>,
+}
+
+impl Default for LabsConfig {
+    fn default() -> Self {
+        Self {
+            sections: default_lab_sections(),
+            analytes: BTreeMap::new(),
+        }
+    }
+}
+
+impl LabsConfig {
+    pub fn builtin_analytes() -> BTreeMap<String, LabAnalyte> {
+        let analytes: [(&str, &[&str], &str, f64, f64); 17] = [
+            ("Sodium", &["Na", "Na+"], "mmol/L", 135.0, 145.0),
+            ("Potassium", &["K", "K+"], "mmol/L", 3.5, 5.0),
+            ("Chloride", &["Cl", "Cl-"], "mmol/L", 98.0, 107.0),
+            (
+                "Bicarbonate",
+                &["HCO3", "CO2", "Bicarb"],
+                "mmol/L",
+                22.0,
+                29.0,
+            ),
+            ("BUN", &["Urea Nitrogen"], "mg/dL", 7.0, 20.0),
+            ("Creatinine", &["Cr", "Creat"], "mg/dL", 0.6, 1.3),
+            ("Glucose", &["Glu", "BG"], "mg/dL", 70.0, 99.0),
+            ("Calcium", &["Ca"], "mg/dL", 8.5, 10.5),
+            ("Magnesium", &["Mg"], "mg/dL", 1.7, 2.2),
+            ("WBC", &["White Count"], "10^3/uL", 4.0, 11.0),
+            ("Hemoglobin", &["Hgb", "Hb"], "g/dL", 12.0, 17.5),
+            ("Hematocrit", &["Hct"], "%", 36.0, 52.0),
+            ("Platelets", &["Plt"], "10^3/uL", 150.0, 400.0),
+            ("Troponin", &["Trop", "TnI"], "ng/mL", 0.0, 0.04),
+            ("HbA1c", &["A1c", "Hemoglobin A1c"], "%", 4.0, 5.6),
+            ("INR", &[], "", 0.8, 1.2),
+            ("Lactate", &["Lactic Acid"], "mmol/L", 0.5, 2.2),
+        ];
+        analytes
+            .iter()
+            .map(|(name, synonyms, unit, low, high)| {
+                (
+                    name.to_string(),
+                    LabAnalyte {
+                        enabled: true,
+                        synonyms: synonyms.iter().map(|s| s.to_string()).collect(),
+                        unit: (!unit.is_empty()).then(|| unit.to_string()),
+                        low: Some(*low),
+                        high: Some(*high),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn resolved_analytes(&self) -> BTreeMap<String, LabAnalyte> {
+        let mut analytes = Self::builtin_analytes();
+        for (name, analyte) in &self.analytes {
+            let name = name.trim();
+            let key = analytes
+                .keys()
+                .find(|known| known.eq_ignore_ascii_case(name))
+                .cloned()
+                .unwrap_or_else(|| name.to_string());
+            if !analyte.enabled {
+                analytes.remove(&key);
+            } else if let Some(base) = analytes.get_mut(&key) {
+                base.merge(analyte);
+            } else {
+                analytes.insert(key, analyte.clone());
+            }
+        }
+        analytes
+    }
+}
+
+impl LabAnalyte {
+    fn merge(&mut self, other: &LabAnalyte) {
+        for synonym in &other.synonyms {
+            if !self
+                .synonyms
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(synonym))
+            {
+                self.synonyms.push(synonym.clone());
+            }
+        }
+        if other.unit.is_some() {
+            self.unit = other.unit.clone();
+        }
+        if other.low.is_some() {
+            self.low = other.low;
+        }
+        if other.high.is_some() {
+            self.high = other.high;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosConfig {
+    #[serde(default = "RosConfig::default_negation")]
+    pub negation: Vec<String>,
+    #[serde(default = "RosConfig::default_post_negation")]
+    pub post_negation: Vec<String>,
+    #[serde(default = "RosConfig::default_uncertainty")]
+    pub uncertainty: Vec<String>,
+    #[serde(default = "RosConfig::default_terminators")]
+    pub terminators: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub systems: BTreeMap<String, RosSystemDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RosSystemDef {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<String>,
+}
+
+impl Default for RosConfig {
+    fn default() -> Self {
+        Self {
+            negation: Self::default_negation(),
+            post_negation: Self::default_post_negation(),
+            uncertainty: Self::default_uncertainty(),
+            terminators: Self::default_terminators(),
+            systems: BTreeMap::new(),
+        }
+    }
+}
+
+impl RosConfig {
+    fn default_negation() -> Vec<String> {
+        to_strings(&[
+            "denies",
+            "denied",
+            "negative for",
+            "no evidence of",
+            "no history of",
+            "no complaints of",
+            "no",
+            "not",
+            "without",
+            "free of",
+            "absence of",
+        ])
+    }
+
+    fn default_post_negation() -> Vec<String> {
+        to_strings(&["denied", "negative", "neg", "absent", "none"])
+    }
+
+    fn default_uncertainty() -> Vec<String> {
+        to_strings(&[
+            "possible",
+            "possibly",
+            "questionable",
+            "question of",
+            "unsure of",
+            "unclear if",
+            "may have",
+            "might have",
+            "probable",
+            "suspected",
+            "rule out",
+            "r/o",
+            "?",
+        ])
+    }
+
+    fn default_terminators() -> Vec<String> {
+        to_strings(&[
+            "but",
+            "however",
+            "although",
+            "except",
+            "aside from",
+            "apart from",
+            "reports",
+            "endorses",
+            "positive for",
+            "complains of",
+            "c/o",
+        ])
+    }
+
+    pub fn builtin_systems() -> BTreeMap<String, RosSystemDef> {
+        let systems: [(&str, &[&str], &[&str]); 14] = [
+            (
+                "Constitutional",
+                &["General", "Gen", "Const"],
+                &[
+                    "fever",
+                    "fevers",
+                    "chills",
+                    "night sweats",
+                    "weight loss",
+                    "weight gain",
+                    "fatigue",
+                    "malaise",
+                    "appetite",
+                ],
+            ),
+            (
+                "Eyes",
+                &["Eye", "Ophthalmologic", "Vision"],
+                &[
+                    "blurred vision",
+                    "vision changes",
+                    "double vision",
+                    "eye pain",
+                ],
+            ),
+            (
+                "ENT",
+                &["HEENT", "ENMT", "Ears Nose Throat"],
+                &[
+                    "sore throat",
+                    "ear pain",
+                    "congestion",
+                    "rhinorrhea",
+                    "sinus pain",
+                    "hearing loss",
+                ],
+            ),
+            (
+                "Cardiovascular",
+                &["CV", "Cardiac", "Cardio", "Heart"],
+                &[
+                    "chest pain",
+                    "cp",
+                    "palpitations",
+                    "edema",
+                    "leg swelling",
+                    "orthopnea",
+                    "syncope",
+                ],
+            ),
+            (
+                "Respiratory",
+                &["Resp", "Pulm", "Pulmonary", "Lungs"],
+                &[
+                    "cough",
+                    "shortness of breath",
+                    "sob",
+                    "dyspnea",
+                    "wheezing",
+                    "hemoptysis",
+                ],
+            ),
+            (
+                "Gastrointestinal",
+                &["GI", "Abdominal", "Abd"],
+                &[
+                    "nausea",
+                    "vomiting",
+                    "diarrhea",
+                    "constipation",
+                    "abdominal pain",
+                    "melena",
+                    "hematochezia",
+                ],
+            ),
+            (
+                "Genitourinary",
+                &["GU", "Urinary"],
+                &["dysuria", "hematuria", "urinary frequency", "urgency"],
+            ),
+            (
+                "Musculoskeletal",
+                &["MSK", "MS"],
+                &[
+                    "joint pain",
+                    "back pain",
+                    "myalgias",
+                    "myalgia",
+                    "arthralgias",
+                ],
+            ),
+            (
+                "Skin",
+                &["Integumentary", "Derm", "Dermatologic"],
+                &["rash", "itching", "pruritus", "lesions"],
+            ),
+            (
+                "Neurological",
+                &["Neuro", "Neurologic"],
+                &[
+                    "headache",
+                    "headaches",
+                    "dizziness",
+                    "numbness",
+                    "tingling",
+                    "weakness",
+                    "seizures",
+                ],
+            ),
+            (
+                "Psychiatric",
+                &["Psych"],
+                &["depression", "anxiety", "suicidal ideation", "insomnia"],
+            ),
+            (
+                "Endocrine",
+                &["Endo"],
+                &[
+                    "polyuria",
+                    "polydipsia",
+                    "heat intolerance",
+                    "cold intolerance",
+                ],
+            ),
+            (
+                "Hematologic",
+                &["Heme", "Heme/Lymph", "Lymphatic"],
+                &["easy bruising", "bruising", "bleeding", "lymphadenopathy"],
+            ),
+            (
+                "Allergic/Immunologic",
+                &["Immunologic", "Allergic", "Immuno"],
+                &["seasonal allergies", "hives"],
+            ),
+        ];
+        systems
+            .iter()
+            .map(|(name, aliases, findings)| {
+                (
+                    name.to_string(),
+                    RosSystemDef {
+                        enabled: true,
+                        aliases: to_strings(aliases),
+                        findings: to_strings(findings),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn resolved_systems(&self) -> BTreeMap<String, RosSystemDef> {
+        let mut systems = Self::builtin_systems();
+        for (name, system) in &self.systems {
+            let name = name.trim();
+            let key = systems
+                .iter()
+                .find(|(known, def)| {
+                    known.eq_ignore_ascii_case(name)
+                        || def.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+                })
+                .map(|(known, _)| known.clone())
+                .unwrap_or_else(|| name.to_string());
+            if !system.enabled {
+                systems.remove(&key);
+            } else if let Some(base) = systems.get_mut(&key) {
+                base.merge(system);
+            } else {
+                systems.insert(key, system.clone());
+            }
+        }
+        systems
+    }
+}
+
+impl RosSystemDef {
+    fn merge(&mut self, other: &RosSystemDef) {
+        for (target, extra) in [
+            (&mut self.aliases, &other.aliases),
+            (&mut self.findings, &other.findings),
+        ] {
+            for entry in extra {
+                if !target.iter().any(|known| known.eq_ignore_ascii_case(entry)) {
+                    target.push(entry.clone());
+                }
+            }
+        }
+    }
+}
+
+fn normalize_format_keys<T>(
+    entries: BTreeMap<String, T>,
+    table: &str,
+) -> std::result::Result<BTreeMap<String, T>, String> {
+    let mut normalized = BTreeMap::new();
+    for (name, value) in entries {
+        let key = name.trim().to_ascii_lowercase();
+        if normalized.insert(key.clone(), value).is_some() {
+            return Err(format!(
+                "[{}.{}] is defined more than once (format names are case-insensitive)",
+                table, key
+            ));
+        }
+    }
+    Ok(normalized)
+}
+
+fn to_strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DelimiterLine {
+    #[default]
+    Drop,
+    Keep,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeaderConfig {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl HeaderConfig {
+    pub fn builtin_fields() -> BTreeMap<String, Vec<String>> {
+        let fields: [(&str, &[&str]); 6] = [
+            ("patient_name", &["Patient", "Patient Name", "Name"]),
+            ("dob", &["DOB", "Date of Birth", "Birth Date"]),
+            ("mrn", &["MRN", "Medical Record Number", "Record Number"]),
+            (
+                "encounter_date",
+                &[
+                    "Date",
+                    "Date of Service",
+                    "DOS",
+                    "Visit Date",
+                    "Encounter Date",
+                ],
+            ),
+            (
+                "provider",
+                &["Provider", "Attending", "Physician", "Clinician"],
+            ),
+            ("location", &["Location", "Clinic", "Facility", "Unit"]),
+        ];
+        fields
+            .iter()
+            .map(|(name, keys)| {
+                (
+                    name.to_string(),
+                    keys.iter().map(|k| k.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    pub fn resolved_fields(&self) -> BTreeMap<String, Vec<String>> {
+        let mut fields = Self::builtin_fields();
+        for (name, keys) in &self.fields {
+            if keys.is_empty() {
+                fields.remove(name);
+            } else {
+                fields.insert(name.clone(), keys.clone());
+            }
+        }
+        fields
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +703,25 @@ fn default_true() -> bool {
     true
 }
 
+fn default_rule_severity() -> Severity {
+    Severity::Warn
+}
+
+fn default_lab_sections() -> Vec<String> {
+    vec![
+        SectionName::Objective.as_str().to_string(),
+        SectionName::HospitalCourse.as_str().to_string(),
+    ]
+}
+
+fn default_fuzzy_distance() -> usize {
+    2
+}
+
+fn default_fuzzy_similarity() -> f64 {
+    0.8
+}
+
 fn default_glob() -> String {
     "*.txt".to_string()
 }
@@ -56,10 +730,20 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             formats: FormatsConfig::default(),
-            heading_aliases: HashMap::new(),
+            sections: BTreeMap::new(),
+            heading_aliases: BTreeMap::new(),
             enable_fallback_heuristics: true,
+            fallback_per_line: true,
+            unmapped_sections: UnmappedPolicy::Narrative,
+            assessment_plan: AssessmentPlanMode::Split,
+            fuzzy_headings: FuzzyHeadingConfig::default(),
+            labs: LabsConfig::default(),
+            ros: RosConfig::default(),
+            heading_styles: HeadingStyle::decorated(),
+            validation: BTreeMap::new(),
             bundle: BundleConfig::default(),
             csv: CsvConfig::default(),
+            header: HeaderConfig::default(),
             glob_default: default_glob(),
         }
     }
@@ -68,38 +752,130 @@ impl Default for Config {
 impl Default for FormatsConfig {
     fn default() -> Self {
         Self {
-            soap: FormatSpec {
-                section_order: vec![
-                    SectionName::Subjective,
-                    SectionName::Objective,
-                    SectionName::Assessment,
-                    SectionName::Plan,
-                ],
-            },
-            hp: FormatSpec {
-                section_order: vec![
-                    SectionName::ChiefComplaint,
-                    SectionName::Hpi,
-                    SectionName::Pmh,
-                    SectionName::Medications,
-                    SectionName::Allergies,
-                    SectionName::Ros,
-                    SectionName::PhysicalExam,
-                    SectionName::Assessment,
-                    SectionName::Plan,
-                ],
-            },
-            discharge: FormatSpec {
-                section_order: vec![
-                    SectionName::AdmissionDx,
-                    SectionName::DischargeDx,
-                    SectionName::HospitalCourse,
-                    SectionName::Medications,
-                    SectionName::FollowUp,
-                    SectionName::Disposition,
-                    SectionName::Instructions,
-                ],
-            },
+            soap: FormatSpec::soap(),
+            hp: FormatSpec::hp(),
+            discharge: FormatSpec::discharge(),
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+impl FormatSpec {
+    fn soap() -> Self {
+        Self {
+            section_order: vec![
+                SectionName::Subjective,
+                SectionName::Objective,
+                SectionName::Assessment,
+                SectionName::Plan,
+            ],
+            required_sections: Vec::new(),
+        }
+    }
+
+    fn hp() -> Self {
+        Self {
+            section_order: vec![
+                SectionName::ChiefComplaint,
+                SectionName::Hpi,
+                SectionName::Pmh,
+                SectionName::Medications,
+                SectionName::Allergies,
+                SectionName::Ros,
+                SectionName::PhysicalExam,
+                SectionName::Assessment,
+                SectionName::Plan,
+            ],
+            required_sections: Vec::new(),
+        }
+    }
+
+    fn discharge() -> Self {
+        Self {
+            section_order: vec![
+                SectionName::AdmissionDx,
+                SectionName::DischargeDx,
+                SectionName::HospitalCourse,
+                SectionName::Medications,
+                SectionName::FollowUp,
+                SectionName::Disposition,
+                SectionName::Instructions,
+            ],
+            required_sections: Vec::new(),
+        }
+    }
+}
+
+impl ValidationSpec {
+    pub fn builtin_required(format: &NoteFormat) -> Vec<RequiredGroup> {
+        match format {
+            NoteFormat::Soap => vec![
+                RequiredGroup::new("Subjective", &["S"]),
+                RequiredGroup::new("Objective", &["O"]),
+                RequiredGroup::new("Assessment", &["A", "Diagnosis", "Dx"]),
+                RequiredGroup::new("Plan", &["P"]),
+            ],
+            NoteFormat::Hp => vec![
+                RequiredGroup::new("HPI", &["History of Present Illness"]),
+                RequiredGroup::new("PMH", &["Past Medical History", "Hx"]),
+                RequiredGroup::new("Medications", &["Meds"]),
+                RequiredGroup::new("Allergies", &["Allergy"]),
+                RequiredGroup::new("Physical Exam", &["Exam", "PE"]),
+                RequiredGroup::new("Assessment", &["Dx", "Diagnosis"]),
+                RequiredGroup::new("Plan", &["P"]),
+            ],
+            NoteFormat::Discharge => vec![
+                RequiredGroup::new("Admission Dx", &["Discharge Dx", "Diagnoses", "Diagnosis"]),
+                RequiredGroup::new("Hospital Course", &["HospitalCourse", "Course"]),
+                RequiredGroup::new("Medications", &["Discharge Meds", "DischargeMeds"]),
+                RequiredGroup::new("Follow-up", &["Follow Up", "FollowUp"]),
+            ],
+            NoteFormat::Custom(_) => Vec::new(),
+        }
+    }
+
+    pub fn builtin_ros_min_systems(format: &NoteFormat) -> usize {
+        match format {
+            NoteFormat::Hp => 2,
+            _ => 0,
+        }
+    }
+
+    pub fn builtin_optional(format: &NoteFormat) -> Vec<String> {
+        let names: &[&str] = match format {
+            NoteFormat::Soap => &["Narrative"],
+            NoteFormat::Hp => &["Chief Complaint", "ROS", "Review of Systems", "Narrative"],
+            NoteFormat::Discharge => &["Disposition", "Instructions", "Narrative"],
+            NoteFormat::Custom(_) => &["Narrative"],
+        };
+        names.iter().map(|n| n.to_string()).collect()
+    }
+}
+
+impl FormatsConfig {
+    pub fn get(&self, format: &NoteFormat) -> Option<&FormatSpec> {
+        match format {
+            NoteFormat::Soap => Some(&self.soap),
+            NoteFormat::Hp => Some(&self.hp),
+            NoteFormat::Discharge => Some(&self.discharge),
+            NoteFormat::Custom(name) => self.custom.get(name),
+        }
+    }
+
+    pub fn names(&self) -> Vec<NoteFormat> {
+        let mut names = NoteFormat::builtin();
+        names.extend(self.custom.keys().map(|k| NoteFormat::Custom(k.clone())));
+        names
+    }
+}
+
+impl Default for FuzzyHeadingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_distance: default_fuzzy_distance(),
+            min_similarity: default_fuzzy_similarity(),
+            strict: false,
         }
     }
 }
@@ -109,6 +885,8 @@ impl Default for BundleConfig {
         Self {
             mode_default: BundleMode::Auto,
             delimiters: vec!["----- NOTE -----".to_string(), "=== VISIT ===".to_string()],
+            delimiter_patterns: Vec::new(),
+            delimiter_line: DelimiterLine::Drop,
         }
     }
 }
@@ -129,22 +907,159 @@ impl Config {
         };
         if candidate.exists() {
             let content = fs::read_to_string(&candidate)?;
-            let config: Config = toml::from_str(&content).map_err(|err| {
-                anyhow!("Failed to parse config {}: {}", candidate.display(), err)
-            })?;
-            Ok(config)
+            Config::from_toml_str(&content)
+                .map_err(|err| anyhow!("Failed to parse config {}: {}", candidate.display(), err))
         } else {
             Ok(Config::default())
         }
     }
 
-    pub fn section_order(&self, format: NoteFormat) -> Vec<String> {
-        let list = match format {
-            NoteFormat::Soap => &self.formats.soap.section_order,
-            NoteFormat::Hp => &self.formats.hp.section_order,
-            NoteFormat::Discharge => &self.formats.discharge.section_order,
-        };
-        list.iter().map(|s| s.as_str().to_string()).collect()
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        let mut config: Config = toml::from_str(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&mut self) -> Result<()> {
+        self.formats.custom =
+            normalize_format_keys(std::mem::take(&mut self.formats.custom), "formats")
+                .map_err(|err| anyhow!(err))?;
+        self.validation = normalize_format_keys(std::mem::take(&mut self.validation), "validation")
+            .map_err(|err| anyhow!(err))?;
+        self.check().map_err(|err| anyhow!(err))
+    }
+
+    pub fn section_order(&self, format: &NoteFormat) -> Vec<String> {
+        self.formats
+            .get(format)
+            .map(|spec| {
+                spec.section_order
+                    .iter()
+                    .map(|s| s.as_str().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn ensure_selection(&self, selection: &FormatSelection) -> Result<()> {
+        match selection {
+            FormatSelection::Auto => Ok(()),
+            FormatSelection::Fixed(format) => self.ensure_format(format),
+        }
+    }
+
+    pub fn ensure_format(&self, format: &NoteFormat) -> Result<()> {
+        if self.formats.get(format).is_some() {
+            return Ok(());
+        }
+        let available: Vec<String> = self
+            .formats
+            .names()
+            .iter()
+            .map(|f| f.as_str().to_string())
+            .collect();
+        Err(anyhow!(
+            "Unknown format '{}' (available: {})",
+            format,
+            available.join(", ")
+        ))
+    }
+
+    fn check(&self) -> std::result::Result<(), String> {
+        if !(0.0..=1.0).contains(&self.fuzzy_headings.min_similarity) {
+            return Err(format!(
+                "fuzzy_headings.min_similarity must be between 0 and 1, got {}",
+                self.fuzzy_headings.min_similarity
+            ));
+        }
+        for (name, analyte) in self.labs.resolved_analytes() {
+            if let (Some(low), Some(high)) = (analyte.low, analyte.high) {
+                if low > high {
+                    return Err(format!(
+                        "Lab analyte '{}' has low {} above high {}",
+                        name, low, high
+                    ));
+                }
+            }
+        }
+        for pattern in &self.bundle.delimiter_patterns {
+            Regex::new(pattern).map_err(|err| {
+                format!("Invalid bundle delimiter pattern '{}': {}", pattern, err)
+            })?;
+        }
+        for name in self.formats.custom.keys() {
+            let format = name
+                .parse::<NoteFormat>()
+                .map_err(|err| format!("Invalid format '{}': {}", name, err))?;
+            if !matches!(format, NoteFormat::Custom(_)) {
+                return Err(format!(
+                    "Custom format '{}' shadows the built-in '{}' format; configure it under [formats.{}]",
+                    name, format, format
+                ));
+            }
+        }
+        for (name, spec) in &self.validation {
+            for rule in &spec.rules {
+                match rule.checks() {
+                    Ok(checks) if checks.is_empty() => {
+                        return Err(format!(
+                            "Content rule '{}' needs must_match, must_not_match, any_of, or none_of",
+                            rule.code
+                        ));
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        return Err(format!(
+                            "Content rule '{}' has an invalid pattern: {}",
+                            rule.code, err
+                        ));
+                    }
+                }
+            }
+            let known = name
+                .parse::<NoteFormat>()
+                .map(|format| self.formats.get(&format).is_some())
+                .unwrap_or(false);
+            if !known {
+                return Err(format!(
+                    "Validation rules declared for unknown format '{}'",
+                    name
+                ));
+            }
+            for code in spec.severity.keys() {
+                let known = ISSUE_CODES.contains(&code.as_str())
+                    || spec.rules.iter().any(|rule| &rule.code == code);
+                if !known {
+                    return Err(format!(
+                        "Unknown issue code '{}' in [validation.{}].severity; expected one of {} or a content rule code",
+                        code,
+                        name,
+                        ISSUE_CODES.join(", ")
+                    ));
+                }
+            }
+        }
+        let registry = SectionRegistry::from_config(self);
+        for format in self.formats.names() {
+            let Some(spec) = self.formats.get(&format) else {
+                continue;
+            };
+            for name in spec.section_order.iter().chain(&spec.required_sections) {
+                if let SectionName::Custom(custom) = name {
+                    if !registry.contains(custom) {
+                        return Err(format!(
+                            "Unknown section '{}' in format '{}'; declare it under [sections.\"{}\"]",
+                            custom, format, custom
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn heading_style_enabled(&self, style: HeadingStyle) -> bool {
+        self.heading_styles.contains(&style)
     }
 
     pub fn resolve_heading_alias(&self, raw: &str) -> Option<String> {
@@ -161,18 +1076,40 @@ impl Config {
     pub fn summary(&self) -> String {
         let mut out = String::new();
         out.push_str("Resolved section order:\n");
-        out.push_str(&format!(
-            "SOAP: {}\n",
-            self.section_order(NoteFormat::Soap).join(", ")
-        ));
-        out.push_str(&format!(
-            "H&P: {}\n",
-            self.section_order(NoteFormat::Hp).join(", ")
-        ));
-        out.push_str(&format!(
-            "Discharge: {}\n",
-            self.section_order(NoteFormat::Discharge).join(", ")
-        ));
+        for format in self.formats.names() {
+            let title = match &format {
+                NoteFormat::Soap => "SOAP",
+                NoteFormat::Hp => "H&P",
+                other => other.label(),
+            };
+            out.push_str(&format!(
+                "{}: {}\n",
+                title,
+                self.section_order(&format).join(", ")
+            ));
+        }
+        out.push_str("\nCustom sections:\n");
+        if self.sections.is_empty() {
+            out.push_str("(none)\n");
+        } else {
+            for (name, def) in &self.sections {
+                let mut details = Vec::new();
+                if let Some(label) = &def.label {
+                    details.push(format!("label: {}", label));
+                }
+                if let Some(code) = &def.code {
+                    details.push(format!("code: {}", code));
+                }
+                if !def.aliases.is_empty() {
+                    details.push(format!("aliases: {}", def.aliases.join(", ")));
+                }
+                if details.is_empty() {
+                    out.push_str(&format!("{}\n", name));
+                } else {
+                    out.push_str(&format!("{} ({})\n", name, details.join("; ")));
+                }
+            }
+        }
         out.push_str("\nHeading aliases:\n");
         if self.heading_aliases.is_empty() {
             out.push_str("(none)\n");
@@ -181,10 +1118,58 @@ impl Config {
                 out.push_str(&format!("{} => {}\n", k, v));
             }
         }
+        out.push_str("\nHeading styles:\n");
+        if self.heading_styles.is_empty() {
+            out.push_str("(plain only)\n");
+        } else {
+            let styles: Vec<String> = self
+                .heading_styles
+                .iter()
+                .map(|style| format!("{:?}", style).to_lowercase())
+                .collect();
+            out.push_str(&format!("{}\n", styles.join(", ")));
+        }
+        out.push_str(&format!(
+            "\nAssessment and Plan: {:?}\n",
+            self.assessment_plan
+        ));
+        out.push_str("\nFuzzy headings:\n");
+        if self.fuzzy_headings.enabled {
+            out.push_str(&format!(
+                "max distance {}, min similarity {:.2}, strict mode {}\n",
+                self.fuzzy_headings.max_distance,
+                self.fuzzy_headings.min_similarity,
+                if self.fuzzy_headings.strict {
+                    "on"
+                } else {
+                    "off"
+                }
+            ));
+        } else {
+            out.push_str("(disabled)\n");
+        }
+        out.push_str("\nLabs:\n");
+        out.push_str(&format!("sections: {}\n", self.labs.sections.join(", ")));
+        let analytes: Vec<String> = self.labs.resolved_analytes().into_keys().collect();
+        out.push_str(&format!("analytes: {}\n", analytes.join(", ")));
+        out.push_str("\nROS systems:\n");
+        let systems: Vec<String> = self.ros.resolved_systems().into_keys().collect();
+        out.push_str(&format!("{}\n", systems.join(", ")));
+        out.push_str("\nHeader fields:\n");
+        for (name, keys) in self.header.resolved_fields() {
+            out.push_str(&format!("{}: {}\n", name, keys.join(", ")));
+        }
         out.push_str("\nBundle delimiters:\n");
         for delimiter in &self.bundle.delimiters {
             out.push_str(&format!("- {}\n", delimiter));
         }
+        for pattern in &self.bundle.delimiter_patterns {
+            out.push_str(&format!("- /{}/\n", pattern));
+        }
+        out.push_str(&format!(
+            "Delimiter lines: {:?}\n",
+            self.bundle.delimiter_line
+        ));
         out
     }
 }
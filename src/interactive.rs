@@ -1,7 +1,20 @@
 use crate::models::SectionCandidate;
+use crate::pipeline::PipelineHooks;
 use anyhow::{anyhow, Result};
 use inquire::{Confirm, MultiSelect, Text};
 
+pub struct InteractiveReviewer;
+
+impl PipelineHooks for InteractiveReviewer {
+    fn review_candidates(
+        &self,
+        _note_index: usize,
+        candidates: Vec<SectionCandidate>,
+    ) -> Result<Vec<SectionCandidate>> {
+        review_sections(&candidates)
+    }
+}
+
 pub fn prompt_apply_heuristics() -> Result<bool> {
     let answer = Confirm::new("Apply fallback heuristics for missing headings?")
         .with_default(true)
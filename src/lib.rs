@@ -1,9 +1,11 @@
 pub mod config;
 pub mod models;
 pub mod parser;
+pub mod pipeline;
 pub mod render;
 pub mod reports;
 pub mod samples;
+pub mod sections;
 pub mod selftest;
 pub mod util;
 pub mod validate;
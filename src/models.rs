@@ -1,13 +1,131 @@
 use clap::ValueEnum;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
-#[clap(rename_all = "lowercase")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum NoteFormat {
+    #[default]
     Soap,
     Hp,
     Discharge,
+    Custom(String),
+}
+
+impl NoteFormat {
+    pub fn builtin() -> Vec<NoteFormat> {
+        vec![NoteFormat::Soap, NoteFormat::Hp, NoteFormat::Discharge]
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            NoteFormat::Soap => "soap",
+            NoteFormat::Hp => "hp",
+            NoteFormat::Discharge => "discharge",
+            NoteFormat::Custom(name) => name,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            NoteFormat::Soap => "Soap",
+            NoteFormat::Hp => "Hp",
+            NoteFormat::Discharge => "Discharge",
+            NoteFormat::Custom(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for NoteFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for NoteFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let name = input.trim().to_ascii_lowercase();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!(
+                "Invalid format name '{}': use letters, digits, '-' or '_'",
+                input
+            ));
+        }
+        Ok(match name.as_str() {
+            "auto" => {
+                return Err("'auto' is reserved for format detection".to_string());
+            }
+            "soap" => NoteFormat::Soap,
+            "hp" => NoteFormat::Hp,
+            "discharge" => NoteFormat::Discharge,
+            _ => NoteFormat::Custom(name),
+        })
+    }
+}
+
+impl Serialize for NoteFormat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for NoteFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatSelection {
+    Auto,
+    Fixed(NoteFormat),
+}
+
+impl From<NoteFormat> for FormatSelection {
+    fn from(format: NoteFormat) -> Self {
+        FormatSelection::Fixed(format)
+    }
+}
+
+impl fmt::Display for FormatSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatSelection::Auto => f.write_str("auto"),
+            FormatSelection::Fixed(format) => format.fmt(f),
+        }
+    }
+}
+
+impl FromStr for FormatSelection {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.trim().eq_ignore_ascii_case("auto") {
+            return Ok(FormatSelection::Auto);
+        }
+        input.parse().map(FormatSelection::Fixed)
+    }
+}
+
+impl Serialize for FormatSelection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FormatSelection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
@@ -17,6 +135,9 @@ pub enum BundleMode {
     Auto,
     On,
     Off,
+    Delimiters,
+    Dates,
+    Headings,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -26,6 +147,35 @@ pub enum CsvLayout {
     Long,
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingStyle {
+    Caps,
+    Colon,
+    #[default]
+    Inline,
+    Markdown,
+    Bold,
+    Numbered,
+    Slash,
+    Dashed,
+    Underlined,
+    Fallback,
+}
+
+impl HeadingStyle {
+    pub fn decorated() -> Vec<HeadingStyle> {
+        vec![
+            HeadingStyle::Markdown,
+            HeadingStyle::Bold,
+            HeadingStyle::Numbered,
+            HeadingStyle::Slash,
+            HeadingStyle::Dashed,
+            HeadingStyle::Underlined,
+        ]
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum WarningSeverity {
@@ -34,105 +184,43 @@ pub enum WarningSeverity {
     Error,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SectionName {
-    #[serde(rename = "Subjective", alias = "S", alias = "SUBJECTIVE")]
     Subjective,
-    #[serde(rename = "Objective", alias = "O", alias = "OBJECTIVE")]
     Objective,
-    #[serde(
-        rename = "Assessment",
-        alias = "A",
-        alias = "ASSESSMENT",
-        alias = "DX",
-        alias = "DIAGNOSIS"
-    )]
     Assessment,
-    #[serde(rename = "Plan", alias = "P", alias = "PLAN")]
     Plan,
-    #[serde(
-        rename = "Chief Complaint",
-        alias = "CC",
-        alias = "CHIEF COMPLAINT",
-        alias = "Chief complaint"
-    )]
+    AssessmentPlan,
     ChiefComplaint,
-    #[serde(
-        rename = "HPI",
-        alias = "History of Present Illness",
-        alias = "HISTORY OF PRESENT ILLNESS"
-    )]
     Hpi,
-    #[serde(
-        rename = "PMH",
-        alias = "Past Medical History",
-        alias = "PAST MEDICAL HISTORY",
-        alias = "HX",
-        alias = "Hx"
-    )]
     Pmh,
-    #[serde(rename = "Medications", alias = "Meds", alias = "MEDS")]
     Medications,
-    #[serde(rename = "Allergies", alias = "Allergy", alias = "ALLERGIES")]
     Allergies,
-    #[serde(
-        rename = "ROS",
-        alias = "Review of Systems",
-        alias = "REVIEW OF SYSTEMS"
-    )]
     Ros,
-    #[serde(
-        rename = "Physical Exam",
-        alias = "PE",
-        alias = "Physical Examination",
-        alias = "PHYSICAL EXAM"
-    )]
     PhysicalExam,
-    #[serde(
-        rename = "Admission Dx",
-        alias = "Admission Diagnosis",
-        alias = "Admit Dx",
-        alias = "ADMISSION DX"
-    )]
     AdmissionDx,
-    #[serde(
-        rename = "Discharge Dx",
-        alias = "Discharge Diagnosis",
-        alias = "Discharge DX"
-    )]
     DischargeDx,
-    #[serde(
-        rename = "Hospital Course",
-        alias = "Course",
-        alias = "HOSPITAL COURSE"
-    )]
     HospitalCourse,
-    #[serde(
-        rename = "Follow-up",
-        alias = "Follow Up",
-        alias = "Followup",
-        alias = "FOLLOW-UP"
-    )]
     FollowUp,
-    #[serde(rename = "Disposition", alias = "Dispo", alias = "DISPOSITION")]
     Disposition,
-    #[serde(
-        rename = "Instructions",
-        alias = "Discharge Instructions",
-        alias = "INSTRUCTIONS"
-    )]
     Instructions,
-    #[serde(rename = "Narrative", alias = "Other")]
     Narrative,
+    Custom(String),
 }
 
 impl SectionName {
-    pub fn as_str(&self) -> &'static str {
+    pub fn parse(raw: &str) -> SectionName {
+        crate::sections::builtin_section(raw)
+            .unwrap_or_else(|| SectionName::Custom(raw.trim().to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
         match self {
             SectionName::Subjective => "Subjective",
             SectionName::Objective => "Objective",
             SectionName::Assessment => "Assessment",
             SectionName::Plan => "Plan",
+            SectionName::AssessmentPlan => "Assessment and Plan",
             SectionName::ChiefComplaint => "Chief Complaint",
             SectionName::Hpi => "HPI",
             SectionName::Pmh => "PMH",
@@ -147,15 +235,55 @@ impl SectionName {
             SectionName::Disposition => "Disposition",
             SectionName::Instructions => "Instructions",
             SectionName::Narrative => "Narrative",
+            SectionName::Custom(name) => name,
+        }
+    }
+}
+
+impl Serialize for SectionName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SectionName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        if crate::util::normalize_heading_key(&raw).is_empty() {
+            return Err(serde::de::Error::custom(format!(
+                "Invalid section name '{}'",
+                raw
+            )));
         }
+        Ok(SectionName::parse(&raw))
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub line_start: usize,
+    pub line_end: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Section {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
     pub content: String,
     pub confidence: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading_span: Option<SourceSpan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading_style: Option<HeadingStyle>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -167,13 +295,29 @@ pub struct ParseWarning {
     pub severity: WarningSeverity,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Metadata {
     pub generated_at: String,
     pub tool_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_detection: Option<FormatDetection>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub delimiter_captures: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatDetection {
+    pub format: NoteFormat,
+    pub score: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner_up: Option<NoteFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runner_up_score: Option<f64>,
+    pub margin: f64,
+    pub ambiguous: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StructuredNote {
     pub id: String,
     pub format: NoteFormat,
@@ -182,6 +326,233 @@ pub struct StructuredNote {
     pub sections: Vec<Section>,
     pub warnings: Vec<ParseWarning>,
     pub metadata: Metadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Encounter>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub header: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<Problem>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub medications: Vec<Medication>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allergies: Option<Allergies>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vitals: Option<Vitals>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labs: Vec<LabResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ros: Option<Ros>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MedicationStatus {
+    Continue,
+    Stop,
+    New,
+}
+
+impl MedicationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MedicationStatus::Continue => "continue",
+            MedicationStatus::Stop => "stop",
+            MedicationStatus::New => "new",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Medication {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strength: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<String>,
+    #[serde(default)]
+    pub prn: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<MedicationStatus>,
+    pub section: String,
+    pub raw: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AllergyStatus {
+    Listed,
+    Nkda,
+    Nka,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Allergies {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<AllergyStatus>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<Allergy>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Allergy {
+    pub agent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    pub raw: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Vitals {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bp_systolic: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bp_diastolic: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heart_rate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub respiratory_rate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_c: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spo2: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub o2_delivery: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_kg: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height_cm: Option<f64>,
+}
+
+impl Vitals {
+    pub fn is_empty(&self) -> bool {
+        self == &Vitals::default()
+    }
+
+    pub fn merge(&mut self, other: Vitals) {
+        self.bp_systolic = self.bp_systolic.or(other.bp_systolic);
+        self.bp_diastolic = self.bp_diastolic.or(other.bp_diastolic);
+        self.heart_rate = self.heart_rate.or(other.heart_rate);
+        self.respiratory_rate = self.respiratory_rate.or(other.respiratory_rate);
+        self.temperature_c = self.temperature_c.or(other.temperature_c);
+        self.spo2 = self.spo2.or(other.spo2);
+        self.o2_delivery = self.o2_delivery.take().or(other.o2_delivery);
+        self.weight_kg = self.weight_kg.or(other.weight_kg);
+        self.height_cm = self.height_cm.or(other.height_cm);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabFlag {
+    Low,
+    Normal,
+    High,
+}
+
+impl LabFlag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabFlag::Low => "low",
+            LabFlag::Normal => "normal",
+            LabFlag::High => "high",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LabResult {
+    pub analyte: String,
+    pub name: String,
+    pub value: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_high: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flag: Option<LabFlag>,
+    pub section: String,
+    pub raw: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<SourceSpan>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingStatus {
+    Present,
+    Absent,
+    Uncertain,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Ros {
+    pub systems: Vec<RosSystem>,
+    #[serde(default)]
+    pub all_other_negative: bool,
+}
+
+impl Ros {
+    pub fn systems_reviewed(&self) -> usize {
+        self.systems
+            .iter()
+            .filter(|s| s.system != RosSystem::UNSPECIFIED)
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RosSystem {
+    pub system: String,
+    #[serde(default)]
+    pub negative: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<RosFinding>,
+}
+
+impl RosSystem {
+    pub const UNSPECIFIED: &'static str = "Unspecified";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RosFinding {
+    pub finding: String,
+    pub status: FindingStatus,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Problem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<usize>,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plan: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Encounter {
+    pub position: usize,
+    pub bundle_size: usize,
+    pub line_start: usize,
+    pub line_end: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_of_service: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_line: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -190,14 +561,66 @@ pub struct HeadingLine {
     pub raw: String,
     pub heading: String,
     pub inline_content: Option<String>,
+    pub token_start: usize,
+    pub token_end: usize,
+    #[serde(default)]
+    pub fallback: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzzy: Option<FuzzyMatch>,
+    #[serde(default)]
+    pub style: HeadingStyle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzyMatch {
+    pub heading: String,
+    pub alias: String,
+    pub distance: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SectionCandidate {
     pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub code: Option<String>,
     pub raw_heading: String,
     pub content: String,
     pub start_line: usize,
     pub end_line: usize,
     pub confidence: f32,
+    #[serde(default)]
+    pub span: Option<SourceSpan>,
+    #[serde(default)]
+    pub heading_span: Option<SourceSpan>,
+    #[serde(default)]
+    pub heading_style: Option<HeadingStyle>,
+    #[serde(skip)]
+    pub heading_columns: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteChunk {
+    pub text: String,
+    pub line_offset: usize,
+    pub byte_offset: usize,
+    pub char_offset: usize,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub captures: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Encounter>,
+}
+
+impl NoteChunk {
+    pub fn whole(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            line_offset: 0,
+            byte_offset: 0,
+            char_offset: 0,
+            captures: BTreeMap::new(),
+            encounter: None,
+        }
+    }
 }
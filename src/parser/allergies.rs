@@ -0,0 +1,164 @@
+use crate::models::{Allergies, Allergy, AllergyStatus, NoteChunk, SectionName, StructuredNote};
+use crate::parser::spans::SectionCursor;
+use crate::util;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+const MAX_AGENT_WORDS: usize = 4;
+
+static BULLET_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:[-*+]|\d{1,2}[.)])\s+").unwrap());
+static NKDA_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:nkda|no known drug allergies|no known medication allergies)$").unwrap()
+});
+static NKA_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?:nka|no known allergies|no allergies|none|none known|denies(?: any)? allergies)$",
+    )
+    .unwrap()
+});
+static UNKNOWN_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:unknown|unk|not known|unable to obtain|unable to assess)$").unwrap()
+});
+static REACTION_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?P<agent>.+?)(?:\s*:\s*|\s+[-–]+>?\s+|\s*->\s*|\s*\(\s*|\s+(?:causes|causing|with)\s+)(?P<rest>.+?)\)?$",
+    )
+    .unwrap()
+});
+static CLAUSE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r";|\.\s+").unwrap());
+static NEGATED_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^(?:no|denies|negative for)\b").unwrap());
+static NOT_AN_AGENT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:not|reviewed|review|see|pending|unsure|uncertain|verify|verified|unverified|updated|refer|tbd|n/a)\b",
+    )
+    .unwrap()
+});
+static SEVERITY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?P<severity>mild|moderate|severe|life[- ]threatening)\b").unwrap()
+});
+
+pub fn note_allergies(note: &StructuredNote, chunk: &NoteChunk) -> Option<Allergies> {
+    let key = util::normalize_heading_key(SectionName::Allergies.as_str());
+    let mut found: Option<Allergies> = None;
+    for section in note
+        .sections
+        .iter()
+        .filter(|s| util::normalize_heading_key(&s.name) == key)
+    {
+        let mut parsed = extract_allergies(&section.content);
+        let mut cursor = SectionCursor::new(chunk, section);
+        for entry in parsed.entries.iter_mut() {
+            entry.span = cursor.as_mut().and_then(|c| c.find(&entry.raw));
+        }
+        let merged = found.get_or_insert_with(Allergies::default);
+        merged.status = merged.status.or(parsed.status);
+        merged.entries.extend(parsed.entries);
+    }
+    found
+}
+
+pub fn extract_allergies(content: &str) -> Allergies {
+    let mut status = None;
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        let line = BULLET_RE
+            .find(line)
+            .map(|m| &line[m.end()..])
+            .unwrap_or(line);
+        for segment in split_segments(line) {
+            if let Some(explicit) = explicit_status(segment) {
+                status = status.or(Some(explicit));
+            } else if let Some(entry) = parse_entry(segment) {
+                entries.push(entry);
+            }
+        }
+    }
+    if status.is_none() && !entries.is_empty() {
+        status = Some(AllergyStatus::Listed);
+    }
+    Allergies { status, entries }
+}
+
+pub fn explicit_status(text: &str) -> Option<AllergyStatus> {
+    let text = text.trim().trim_end_matches('.').trim();
+    if NKDA_RE.is_match(text) {
+        Some(AllergyStatus::Nkda)
+    } else if NKA_RE.is_match(text) {
+        Some(AllergyStatus::Nka)
+    } else if UNKNOWN_RE.is_match(text) {
+        Some(AllergyStatus::Unknown)
+    } else {
+        None
+    }
+}
+
+fn split_segments(line: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    for part in CLAUSE_RE
+        .split(line)
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        let pieces: Vec<&str> = part
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        let with_reaction = pieces.iter().filter(|p| REACTION_RE.is_match(p)).count();
+        if pieces.len() > 1 && (with_reaction == 0 || with_reaction == pieces.len()) {
+            segments.extend(pieces);
+        } else {
+            segments.push(part);
+        }
+    }
+    segments
+}
+
+fn parse_entry(segment: &str) -> Option<Allergy> {
+    let raw = segment.trim().trim_end_matches('.').trim();
+    if raw.is_empty() || raw.ends_with(':') || NEGATED_RE.is_match(raw) {
+        return None;
+    }
+    let (agent, rest) = match REACTION_RE.captures(raw) {
+        Some(caps) => (
+            caps["agent"].trim().to_string(),
+            Some(caps["rest"].to_string()),
+        ),
+        None => (raw.to_string(), None),
+    };
+    if agent.split_whitespace().count() > MAX_AGENT_WORDS
+        || !agent.chars().any(char::is_alphabetic)
+        || NOT_AN_AGENT_RE.is_match(&agent)
+    {
+        return None;
+    }
+
+    let mut severity = None;
+    let reaction = rest.and_then(|rest| {
+        let mut reaction = rest.clone();
+        if let Some(found) = SEVERITY_RE.captures(&rest) {
+            severity = Some(found["severity"].to_lowercase().replace(' ', "-"));
+            reaction.replace_range(found.get(0).unwrap().range(), "");
+        }
+        let reaction = reaction
+            .trim_matches(|c: char| c.is_whitespace() || matches!(c, ',' | '(' | ')' | '-' | ':'))
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if reaction.is_empty() {
+            None
+        } else {
+            Some(reaction)
+        }
+    });
+
+    Some(Allergy {
+        agent,
+        reaction,
+        severity,
+        raw: raw.to_string(),
+        span: None,
+    })
+}
@@ -1,94 +1,349 @@
-use crate::config::Config;
-use crate::models::{BundleMode, ParseWarning, WarningSeverity};
-use crate::parser::warnings;
+use crate::config::{BundleConfig, Config, DelimiterLine};
+use crate::models::{BundleMode, Encounter, NoteChunk, ParseWarning, WarningSeverity};
+use crate::parser::spans::LineIndex;
+use crate::parser::{headings, normalize, warnings};
+use crate::util;
+use chrono::NaiveDate;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
 
 static DATE_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^\s*(\d{4}-\d{2}-\d{2}|\d{2}/\d{2}/\d{4})").unwrap());
+static SERVICE_DATE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:(?P<iso>\d{4}-\d{2}-\d{2})|(?P<m>\d{1,2})/(?P<d>\d{1,2})/(?P<y>\d{4}))\b")
+        .unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitStrategy {
+    Delimiters,
+    Dates,
+    HeadingResets,
+}
+
+impl SplitStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SplitStrategy::Delimiters => "delimiters",
+            SplitStrategy::Dates => "dates",
+            SplitStrategy::HeadingResets => "heading resets",
+        }
+    }
+}
+
+const CASCADE: [SplitStrategy; 3] = [
+    SplitStrategy::Delimiters,
+    SplitStrategy::Dates,
+    SplitStrategy::HeadingResets,
+];
 
 pub fn split_bundle(
     text: &str,
     mode: BundleMode,
     config: &Config,
-) -> (Vec<String>, Vec<ParseWarning>) {
+) -> (Vec<NoteChunk>, Vec<ParseWarning>) {
+    let (mut chunks, warnings_list) = split_with_mode(text, mode, config);
+    annotate_encounters(&mut chunks);
+    (chunks, warnings_list)
+}
+
+fn split_with_mode(
+    text: &str,
+    mode: BundleMode,
+    config: &Config,
+) -> (Vec<NoteChunk>, Vec<ParseWarning>) {
     match mode {
-        BundleMode::Off => (vec![text.to_string()], Vec::new()),
-        BundleMode::On => split_bundle_internal(text, config, true),
-        BundleMode::Auto => split_bundle_internal(text, config, false),
+        BundleMode::Off => (vec![NoteChunk::whole(text)], Vec::new()),
+        BundleMode::On => split_bundle_internal(text, config, &CASCADE, true),
+        BundleMode::Auto => split_bundle_internal(text, config, &CASCADE, false),
+        BundleMode::Delimiters => {
+            split_bundle_internal(text, config, &[SplitStrategy::Delimiters], true)
+        }
+        BundleMode::Dates => split_bundle_internal(text, config, &[SplitStrategy::Dates], true),
+        BundleMode::Headings => {
+            split_bundle_internal(text, config, &[SplitStrategy::HeadingResets], true)
+        }
     }
 }
 
 fn split_bundle_internal(
     text: &str,
     config: &Config,
+    strategies: &[SplitStrategy],
     strict: bool,
-) -> (Vec<String>, Vec<ParseWarning>) {
+) -> (Vec<NoteChunk>, Vec<ParseWarning>) {
+    let index = LineIndex::new(text);
+    for strategy in strategies {
+        let notes = match strategy {
+            SplitStrategy::Delimiters => split_on_delimiters(text, &index, &config.bundle),
+            SplitStrategy::Dates => split_on_dates(text, &index),
+            SplitStrategy::HeadingResets => split_on_heading_resets(text, &index, config),
+        };
+        let captured = notes.iter().any(|n| !n.captures.is_empty());
+        if notes.len() > 1 || captured {
+            let warnings_list = split_warnings(*strategy, &notes, &index);
+            return (notes, warnings_list);
+        }
+    }
+
     let mut warnings_list = Vec::new();
-    let mut notes = split_on_delimiters(text, &config.bundle.delimiters);
-    if notes.len() <= 1 {
-        notes = split_on_dates(text);
-    }
-
-    if notes.len() <= 1 {
-        if strict {
-            warnings_list.push(warnings::warning(
-                "bundle_not_split",
-                "Bundle mode requested but no clear delimiters found".to_string(),
-                1,
-                text.lines().count().max(1),
-                WarningSeverity::Warning,
-            ));
+    if strict {
+        warnings_list.push(warnings::warning(
+            "bundle_not_split",
+            "Bundle mode requested but no clear delimiters found".to_string(),
+            1,
+            index.len().max(1),
+            WarningSeverity::Warning,
+        ));
+    }
+    (vec![NoteChunk::whole(text)], warnings_list)
+}
+
+fn annotate_encounters(chunks: &mut [NoteChunk]) {
+    let total = chunks.len();
+    let split = total > 1
+        || chunks.iter().any(|chunk| {
+            chunk.encounter.is_some()
+                || !chunk.captures.is_empty()
+                || DATE_RE.is_match(LineIndex::new(&chunk.text).line_text(1).trim())
+        });
+    if !split {
+        return;
+    }
+    for (idx, chunk) in chunks.iter_mut().enumerate() {
+        let lines = LineIndex::new(&chunk.text);
+        let encounter = chunk.encounter.get_or_insert_with(Encounter::default);
+        encounter.position = idx + 1;
+        encounter.bundle_size = total;
+        encounter.line_start = chunk.line_offset + 1;
+        encounter.line_end = chunk.line_offset + lines.len().max(1);
+        if encounter.header_line.is_none() {
+            let first = lines.line_text(1).trim();
+            if DATE_RE.is_match(first) {
+                encounter.header_line = Some(first.to_string());
+            }
+        }
+        if encounter.date_of_service.is_none() {
+            encounter.date_of_service = chunk
+                .captures
+                .get("date")
+                .map(|raw| service_date(raw).unwrap_or_else(|| raw.clone()))
+                .or_else(|| encounter.header_line.as_deref().and_then(service_date));
         }
-        return (vec![text.to_string()], warnings_list);
     }
+}
+
+pub fn service_date(raw: &str) -> Option<String> {
+    let caps = SERVICE_DATE_RE.captures(raw)?;
+    let date = if let Some(iso) = caps.name("iso") {
+        NaiveDate::parse_from_str(iso.as_str(), "%Y-%m-%d").ok()?
+    } else {
+        let month = caps.name("m")?.as_str().parse().ok()?;
+        let day = caps.name("d")?.as_str().parse().ok()?;
+        let year = caps.name("y")?.as_str().parse().ok()?;
+        NaiveDate::from_ymd_opt(year, month, day)?
+    };
+    Some(date.format("%Y-%m-%d").to_string())
+}
 
-    (notes, warnings_list)
+fn split_warnings(
+    strategy: SplitStrategy,
+    notes: &[NoteChunk],
+    index: &LineIndex,
+) -> Vec<ParseWarning> {
+    let mut found = vec![warnings::warning(
+        "bundle_strategy",
+        format!(
+            "Bundle split into {} note(s) using {}",
+            notes.len(),
+            strategy.as_str()
+        ),
+        1,
+        index.len().max(1),
+        WarningSeverity::Info,
+    )];
+    for (idx, note) in notes.iter().enumerate().skip(1) {
+        let line = note.line_offset + 1;
+        found.push(warnings::warning(
+            "bundle_split",
+            format!("Note {} starts at line {}", idx + 1, line),
+            line,
+            line,
+            WarningSeverity::Info,
+        ));
+    }
+    found
 }
 
-fn split_on_delimiters(text: &str, delimiters: &[String]) -> Vec<String> {
+fn split_on_delimiters(text: &str, index: &LineIndex, bundle: &BundleConfig) -> Vec<NoteChunk> {
+    let patterns: Vec<Regex> = bundle
+        .delimiter_patterns
+        .iter()
+        .filter_map(|p| Regex::new(p).ok())
+        .collect();
+    let keep = bundle.delimiter_line == DelimiterLine::Keep;
     let mut notes = Vec::new();
-    let mut current = Vec::new();
-    for line in text.lines() {
-        let trimmed = line.trim();
-        if delimiters.iter().any(|d| d.trim() == trimmed) {
-            if !current.is_empty() {
-                notes.push(current.join("\n").trim().to_string());
-                current.clear();
-            }
+    let mut current_start = 1;
+    let mut captures = BTreeMap::new();
+    let mut header = None;
+    for line_num in 1..=index.len() {
+        let Some(found) = match_delimiter(index.line_text(line_num), &bundle.delimiters, &patterns)
+        else {
             continue;
+        };
+        if let Some(mut chunk) = chunk_lines(text, index, current_start, line_num - 1) {
+            chunk.captures = std::mem::take(&mut captures);
+            chunk.encounter = header.take().map(|line| Encounter {
+                header_line: Some(line),
+                ..Default::default()
+            });
+            notes.push(chunk);
         }
-        current.push(line.to_string());
+        captures = found;
+        header = Some(index.line_text(line_num).trim().to_string());
+        current_start = if keep { line_num } else { line_num + 1 };
     }
-    if !current.is_empty() {
-        notes.push(current.join("\n").trim().to_string());
+    if let Some(mut chunk) = chunk_lines(text, index, current_start, index.len()) {
+        chunk.captures = captures;
+        chunk.encounter = header.map(|line| Encounter {
+            header_line: Some(line),
+            ..Default::default()
+        });
+        notes.push(chunk);
     }
     if notes.is_empty() {
-        notes.push(text.to_string());
+        notes.push(NoteChunk::whole(text));
     }
     notes
 }
 
-fn split_on_dates(text: &str) -> Vec<String> {
+fn match_delimiter(
+    line: &str,
+    delimiters: &[String],
+    patterns: &[Regex],
+) -> Option<BTreeMap<String, String>> {
+    let trimmed = line.trim();
+    if delimiters.iter().any(|d| d.trim() == trimmed) {
+        return Some(BTreeMap::new());
+    }
+    patterns.iter().find_map(|re| {
+        let caps = re.captures(trimmed)?;
+        Some(
+            re.capture_names()
+                .flatten()
+                .filter_map(|name| {
+                    let value = caps.name(name)?.as_str().trim();
+                    (!value.is_empty()).then(|| (name.to_string(), value.to_string()))
+                })
+                .collect(),
+        )
+    })
+}
+
+fn split_on_dates(text: &str, index: &LineIndex) -> Vec<NoteChunk> {
     let mut notes = Vec::new();
-    let mut current = Vec::new();
+    let mut current_start = 1;
     let mut found = 0;
-    for line in text.lines() {
-        if DATE_RE.is_match(line) {
-            if !current.is_empty() {
-                notes.push(current.join("\n").trim().to_string());
-                current.clear();
+    for line_num in 1..=index.len() {
+        if DATE_RE.is_match(index.line_text(line_num)) {
+            if line_num > current_start {
+                notes.extend(chunk_lines(text, index, current_start, line_num - 1));
             }
+            current_start = line_num;
             found += 1;
         }
-        current.push(line.to_string());
-    }
-    if !current.is_empty() {
-        notes.push(current.join("\n").trim().to_string());
     }
+    notes.extend(chunk_lines(text, index, current_start, index.len()));
     if found <= 1 {
-        vec![text.to_string()]
+        vec![NoteChunk::whole(text)]
     } else {
         notes
     }
 }
+
+fn split_on_heading_resets(text: &str, index: &LineIndex, config: &Config) -> Vec<NoteChunk> {
+    let orders: Vec<Vec<String>> = config
+        .formats
+        .names()
+        .iter()
+        .map(|format| {
+            config
+                .section_order(format)
+                .iter()
+                .map(|name| util::normalize_heading_key(name))
+                .collect()
+        })
+        .filter(|order: &Vec<String>| !order.is_empty())
+        .collect();
+    let normalized = normalize::normalize_text(text);
+    let lines: Vec<String> = normalized.lines().map(|l| l.to_string()).collect();
+    let found: Vec<(usize, String)> = headings::scan_headings(&lines, config)
+        .into_iter()
+        .map(|h| (h.line_num, util::normalize_heading_key(&h.heading)))
+        .collect();
+
+    let mut starts = vec![1];
+    let mut segment: HashSet<&str> = HashSet::new();
+    for (pos, (line_num, key)) in found.iter().enumerate() {
+        let restarts = orders
+            .iter()
+            .filter(|order| order[0] == *key)
+            .any(|order| restarts_sequence(order, &segment, &found[pos + 1..]));
+        if restarts {
+            starts.push(*line_num);
+            segment.clear();
+        }
+        segment.insert(key);
+    }
+    if starts.len() <= 1 {
+        return vec![NoteChunk::whole(text)];
+    }
+
+    let mut notes = Vec::new();
+    for (idx, start) in starts.iter().enumerate() {
+        let end = starts
+            .get(idx + 1)
+            .map(|next| next - 1)
+            .unwrap_or(index.len());
+        notes.extend(chunk_lines(text, index, *start, end));
+    }
+    notes
+}
+
+fn restarts_sequence(order: &[String], segment: &HashSet<&str>, rest: &[(usize, String)]) -> bool {
+    let later = |key: &str| order[1..].iter().any(|name| name == key);
+    if !segment.iter().any(|key| later(key)) {
+        return false;
+    }
+    rest.iter()
+        .take_while(|(_, key)| *key != order[0])
+        .any(|(_, key)| later(key) && segment.contains(key.as_str()))
+}
+
+fn chunk_lines(
+    text: &str,
+    index: &LineIndex,
+    mut first: usize,
+    mut last: usize,
+) -> Option<NoteChunk> {
+    while first <= last && index.line_text(first).trim().is_empty() {
+        first += 1;
+    }
+    while last >= first && index.line_text(last).trim().is_empty() {
+        last -= 1;
+    }
+    if first > last {
+        return None;
+    }
+    let start = index.line(first)?;
+    let end = index.line(last)?;
+    Some(NoteChunk {
+        text: text[start.byte_start..end.byte_end].to_string(),
+        line_offset: first - 1,
+        byte_offset: start.byte_start,
+        char_offset: start.char_start,
+        captures: BTreeMap::new(),
+        encounter: None,
+    })
+}
@@ -0,0 +1,87 @@
+use crate::config::Config;
+use crate::models::{FormatDetection, NoteFormat, ParseWarning, WarningSeverity};
+use crate::parser::{headings, normalize, spans, warnings};
+use crate::util;
+use std::collections::BTreeSet;
+
+pub const AMBIGUOUS_MARGIN: f64 = 1.0;
+
+pub fn score_formats(text: &str, config: &Config) -> Vec<(NoteFormat, f64)> {
+    let normalized = normalize::normalize_text(text);
+    let lines: Vec<String> = normalized.lines().map(|l| l.to_string()).collect();
+    let found: BTreeSet<String> = headings::scan_headings(&lines, config)
+        .iter()
+        .map(|h| util::normalize_heading_key(&h.heading))
+        .filter(|key| key != "NARRATIVE")
+        .collect();
+
+    config
+        .formats
+        .names()
+        .into_iter()
+        .map(|format| {
+            let order: BTreeSet<String> = config
+                .section_order(&format)
+                .iter()
+                .map(|name| util::normalize_heading_key(name))
+                .collect();
+            let hits = found.intersection(&order).count() as f64;
+            let extra = found.difference(&order).count() as f64;
+            let coverage = if order.is_empty() {
+                0.0
+            } else {
+                hits / order.len() as f64
+            };
+            (format, round_score(hits - extra + coverage))
+        })
+        .collect()
+}
+
+pub fn detect_format(text: &str, config: &Config) -> (FormatDetection, Vec<ParseWarning>) {
+    let mut scores = score_formats(text, config);
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let (format, score) = scores.first().cloned().unwrap_or((NoteFormat::Soap, 0.0));
+    let runner_up = scores.get(1).cloned();
+    let margin = round_score(runner_up.as_ref().map(|(_, s)| score - s).unwrap_or(score));
+    let ambiguous = score <= 0.0 || margin < AMBIGUOUS_MARGIN;
+
+    let mut found = Vec::new();
+    if ambiguous {
+        let line_end = spans::LineIndex::new(text).len().max(1);
+        let message = if score <= 0.0 {
+            format!(
+                "No headings matched a configured format; defaulting to {}",
+                format
+            )
+        } else {
+            match &runner_up {
+                Some((other, other_score)) => format!(
+                    "Detected format {} ({:.2}) is close to {} ({:.2})",
+                    format, score, other, other_score
+                ),
+                None => format!("Detected format {} with low score {:.2}", format, score),
+            }
+        };
+        found.push(warnings::warning(
+            "format_ambiguous",
+            message,
+            1,
+            line_end,
+            WarningSeverity::Warning,
+        ));
+    }
+
+    let detection = FormatDetection {
+        format,
+        score,
+        runner_up_score: runner_up.as_ref().map(|(_, s)| *s),
+        runner_up: runner_up.map(|(f, _)| f),
+        margin,
+        ambiguous,
+    };
+    (detection, found)
+}
+
+fn round_score(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,39 @@
+use crate::config::Config;
+use crate::parser::{headings, normalize};
+use crate::util;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+static PAIR_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?P<k>[A-Za-z][A-Za-z #./]{0,30}?)\s*:\s*(?P<v>.+)$").unwrap());
+static SEPARATOR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s*[|;]\s*|\s{2,}").unwrap());
+
+pub fn extract_header(text: &str, config: &Config) -> BTreeMap<String, String> {
+    let mut lookup = HashMap::new();
+    for (field, keys) in config.header.resolved_fields() {
+        for key in keys {
+            lookup.insert(util::normalize_heading_key(&key), field.clone());
+        }
+    }
+
+    let normalized = normalize::normalize_text(text);
+    let mut header = BTreeMap::new();
+    for line in normalized.lines() {
+        if headings::match_heading(line, config).is_some() {
+            break;
+        }
+        for segment in SEPARATOR_RE.split(line.trim()) {
+            let Some(caps) = PAIR_RE.captures(segment) else {
+                continue;
+            };
+            let key = util::normalize_heading_key(&caps["k"]);
+            if let Some(field) = lookup.get(&key) {
+                header
+                    .entry(field.clone())
+                    .or_insert_with(|| caps["v"].trim().to_string());
+            }
+        }
+    }
+    header
+}
@@ -1,116 +1,507 @@
 use crate::config::Config;
-use crate::models::HeadingLine;
+use crate::models::{FuzzyMatch, HeadingLine, HeadingStyle};
+use crate::sections::{self, SectionRegistry};
 use crate::util;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::HashSet;
 
 static INLINE_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(?P<h>[A-Za-z0-9 /&.-]{1,40}):\s*(?P<rest>.+)$").unwrap());
 static COLON_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^(?P<h>[A-Za-z0-9 /&.-]{2,40}):\s*$").unwrap());
+static HEADING_TOKEN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z][A-Za-z /&.-]{0,39}$").unwrap());
 static ALL_CAPS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Z][A-Z0-9 /&-]{1,40}$").unwrap());
-
-static HEADING_MAP: Lazy<HashMap<String, String>> = Lazy::new(|| {
-    let mut map = HashMap::new();
-    let pairs = vec![
-        ("SUBJECTIVE", "Subjective"),
-        ("OBJECTIVE", "Objective"),
-        ("ASSESSMENT", "Assessment"),
-        ("DIAGNOSIS", "Assessment"),
-        ("DX", "Assessment"),
-        ("PLAN", "Plan"),
-        ("S", "Subjective"),
-        ("O", "Objective"),
-        ("A", "Assessment"),
-        ("P", "Plan"),
-        ("CHIEF COMPLAINT", "Chief Complaint"),
-        ("CC", "Chief Complaint"),
-        ("HPI", "HPI"),
-        ("HISTORY OF PRESENT ILLNESS", "HPI"),
-        ("PMH", "PMH"),
-        ("PAST MEDICAL HISTORY", "PMH"),
-        ("HX", "PMH"),
-        ("MEDS", "Medications"),
-        ("MEDICATIONS", "Medications"),
-        ("ALLERGIES", "Allergies"),
-        ("ALLERGY", "Allergies"),
-        ("ROS", "ROS"),
-        ("REVIEW OF SYSTEMS", "ROS"),
-        ("PHYSICAL EXAM", "Physical Exam"),
-        ("PHYSICAL EXAMINATION", "Physical Exam"),
-        ("PE", "Physical Exam"),
-        ("ADMISSION DX", "Admission Dx"),
-        ("ADMISSION DIAGNOSIS", "Admission Dx"),
-        ("ADMIT DX", "Admission Dx"),
-        ("DISCHARGE DX", "Discharge Dx"),
-        ("DISCHARGE DIAGNOSIS", "Discharge Dx"),
-        ("HOSPITAL COURSE", "Hospital Course"),
-        ("COURSE", "Hospital Course"),
-        ("FOLLOW UP", "Follow-up"),
-        ("FOLLOW-UP", "Follow-up"),
-        ("FOLLOWUP", "Follow-up"),
-        ("DISPOSITION", "Disposition"),
-        ("DISPO", "Disposition"),
-        ("INSTRUCTIONS", "Instructions"),
-        ("DISCHARGE INSTRUCTIONS", "Instructions"),
-    ];
-    for (k, v) in pairs {
-        map.insert(k.to_string(), v.to_string());
-    }
-    map
+static MARKDOWN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^#{1,6}\s+(?P<h>.+?)\s*#*$").unwrap());
+static BOLD_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:\*\*|__)(?P<h>[^*_]{1,40}?)(?:\*\*|__)(?P<rest>.*)$").unwrap());
+static NUMBERED_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:\d{1,2}|[IVX]{1,4})[.)]\s+(?P<h>.+)$").unwrap());
+static DASHED_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[-=*~]{2,}\s*(?P<h>[A-Za-z](?:.*?[A-Za-z0-9:])?)\s*[-=*~]{2,}$").unwrap()
 });
+static LIST_ITEM_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:[-*+•]|\d{1,2}[.)]|[IVX]{1,4}[.)])\s+").unwrap());
+static UNDERLINE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:={3,}|-{3,}|~{3,})$").unwrap());
+
+const MAX_INLINE_HEADING_WORDS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct HeadingMatch {
+    pub heading: String,
+    pub inline_content: Option<String>,
+    pub token_start: usize,
+    pub token_end: usize,
+    pub style: HeadingStyle,
+}
 
 pub fn scan_headings(lines: &[String], config: &Config) -> Vec<HeadingLine> {
+    let underlines = config.heading_style_enabled(HeadingStyle::Underlined);
     let mut headings = Vec::new();
     for (idx, line) in lines.iter().enumerate() {
-        if let Some((heading, inline)) = detect_heading(line, config) {
+        let mut found_on_line = match_headings(line, config);
+        let underlined = underlines
+            && lines
+                .get(idx + 1)
+                .is_some_and(|next| UNDERLINE_RE.is_match(next.trim()));
+        if underlined {
+            match found_on_line.as_mut_slice() {
+                [] => found_on_line.extend(match_underlined(line, config)),
+                [only] if only.inline_content.is_none() => only.style = HeadingStyle::Underlined,
+                _ => {}
+            }
+        }
+        for found in found_on_line {
             headings.push(HeadingLine {
                 line_num: idx + 1,
                 raw: line.clone(),
-                heading,
-                inline_content: inline,
+                heading: found.heading,
+                inline_content: found.inline_content,
+                token_start: found.token_start,
+                token_end: found.token_end,
+                fallback: false,
+                fuzzy: None,
+                style: found.style,
             });
         }
     }
+    drop_numbered_list_items(lines, &mut headings);
     headings
 }
 
 pub fn detect_heading(line: &str, config: &Config) -> Option<(String, Option<String>)> {
+    match_heading(line, config).map(|found| (found.heading, found.inline_content))
+}
+
+pub fn match_heading(line: &str, config: &Config) -> Option<HeadingMatch> {
+    match_heading_with(line, config, |raw| canonicalize_heading(raw, config))
+}
+
+pub fn fuzzy_headings(
+    lines: &[String],
+    config: &Config,
+    registry: &SectionRegistry,
+) -> Vec<HeadingLine> {
+    let aliases = alias_table(registry, config);
+    let mut headings = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let matched = RefCell::new(None);
+        let found = match_heading_with(line, config, |raw| {
+            let fuzzy = fuzzy_canonicalize(raw, &aliases, config)?;
+            let heading = fuzzy.heading.clone();
+            matched.replace(Some(fuzzy));
+            Some(heading)
+        });
+        if let Some(found) = found {
+            headings.push(HeadingLine {
+                line_num: idx + 1,
+                raw: line.clone(),
+                fuzzy: matched.into_inner(),
+                heading: found.heading,
+                inline_content: found.inline_content,
+                token_start: found.token_start,
+                token_end: found.token_end,
+                fallback: false,
+                style: found.style,
+            });
+        }
+    }
+    drop_numbered_list_items(lines, &mut headings);
+    headings
+}
+
+fn drop_numbered_list_items(lines: &[String], headings: &mut Vec<HeadingLine>) {
+    let mut dropped = HashSet::new();
+    let mut in_list = false;
+    let mut found = headings.iter().peekable();
+    for (idx, line) in lines.iter().enumerate() {
+        let mut is_heading = false;
+        while let Some(heading) = found.next_if(|h| h.line_num == idx + 1) {
+            if in_list && heading.style == HeadingStyle::Numbered {
+                dropped.insert(heading.line_num);
+            } else {
+                is_heading = true;
+            }
+        }
+        if is_heading {
+            in_list = false;
+        } else if LIST_ITEM_RE.is_match(line.trim()) {
+            in_list = true;
+        }
+    }
+    headings.retain(|h| !dropped.contains(&h.line_num));
+}
+
+fn alias_table(registry: &SectionRegistry, config: &Config) -> Vec<(String, String)> {
+    let mut table = Vec::new();
+    for entry in registry.entries() {
+        table.push((entry.name.clone(), entry.name.clone()));
+        for alias in &entry.aliases {
+            table.push((alias.clone(), entry.name.clone()));
+        }
+    }
+    for (alias, target) in &config.heading_aliases {
+        table.push((alias.clone(), target.clone()));
+    }
+    table
+}
+
+fn fuzzy_canonicalize(
+    raw: &str,
+    aliases: &[(String, String)],
+    config: &Config,
+) -> Option<FuzzyMatch> {
+    let settings = &config.fuzzy_headings;
+    let key = util::normalize_heading_key(raw);
+    if key.is_empty() {
+        return None;
+    }
+    let mut best: Option<FuzzyMatch> = None;
+    let mut tied = false;
+    for (alias, target) in aliases {
+        let alias_key = util::normalize_heading_key(alias);
+        let distance = util::edit_distance(&key, &alias_key);
+        let longest = key.chars().count().max(alias_key.chars().count());
+        let similarity = 1.0 - distance as f64 / longest as f64;
+        if distance > settings.max_distance || similarity < settings.min_similarity {
+            continue;
+        }
+        match &best {
+            Some(current) if distance > current.distance => {}
+            Some(current) if distance == current.distance => {
+                if util::normalize_heading_key(&current.heading)
+                    != util::normalize_heading_key(target)
+                {
+                    tied = true;
+                }
+            }
+            _ => {
+                tied = false;
+                best = Some(FuzzyMatch {
+                    heading: target.clone(),
+                    alias: alias.clone(),
+                    distance,
+                });
+            }
+        }
+    }
+    if tied {
+        None
+    } else {
+        best
+    }
+}
+
+fn match_heading_with(
+    line: &str,
+    config: &Config,
+    resolve: impl Fn(&str) -> Option<String>,
+) -> Option<HeadingMatch> {
     let trimmed = line.trim();
     if trimmed.is_empty() {
         return None;
     }
+    let lead = line.len() - line.trim_start().len();
+
+    if let Some(found) = match_plain(trimmed, lead, &resolve) {
+        return Some(found);
+    }
+    let slashed = config.heading_style_enabled(HeadingStyle::Slash);
+    if slashed {
+        let resolve_slashed = |raw: &str| resolve_slashed(raw, &resolve);
+        if let Some(found) = match_plain(trimmed, lead, &resolve_slashed) {
+            return Some(HeadingMatch {
+                style: HeadingStyle::Slash,
+                ..found
+            });
+        }
+    }
+    let resolve_any = |raw: &str| {
+        resolve(raw).or_else(|| {
+            if slashed {
+                resolve_slashed(raw, &resolve)
+            } else {
+                None
+            }
+        })
+    };
+    match_decorated(trimmed, lead, config, &resolve_any)
+}
 
+fn match_plain(
+    trimmed: &str,
+    lead: usize,
+    resolve: &impl Fn(&str) -> Option<String>,
+) -> Option<HeadingMatch> {
     if let Some(caps) = ALL_CAPS_RE.captures(trimmed) {
         let raw = caps.get(0).map(|m| m.as_str()).unwrap_or("");
-        if let Some(mapped) = canonicalize_heading(raw, config) {
-            return Some((mapped, None));
+        if let Some(mapped) = resolve(raw) {
+            return Some(HeadingMatch {
+                heading: mapped,
+                inline_content: None,
+                token_start: lead,
+                token_end: lead + trimmed.len(),
+                style: HeadingStyle::Caps,
+            });
         }
     }
 
     if let Some(caps) = COLON_RE.captures(trimmed) {
         let raw = caps.name("h").map(|m| m.as_str()).unwrap_or("");
-        if let Some(mapped) = canonicalize_heading(raw, config) {
-            return Some((mapped, None));
+        if let Some(mapped) = resolve(raw) {
+            return Some(HeadingMatch {
+                heading: mapped,
+                inline_content: None,
+                token_start: lead,
+                token_end: lead + trimmed.len(),
+                style: HeadingStyle::Colon,
+            });
         }
     }
 
     if let Some(caps) = INLINE_RE.captures(trimmed) {
-        let raw = caps.name("h").map(|m| m.as_str()).unwrap_or("");
-        let rest = caps.name("rest").map(|m| m.as_str()).unwrap_or("");
-        if let Some(mapped) = canonicalize_heading(raw, config) {
-            return Some((mapped, Some(rest.trim().to_string())));
+        if let Some(h) = caps.name("h") {
+            let rest = caps.name("rest").map(|m| m.as_str()).unwrap_or("");
+            if let Some(mapped) = resolve(h.as_str()) {
+                return Some(HeadingMatch {
+                    heading: mapped,
+                    inline_content: Some(rest.trim().to_string()),
+                    token_start: lead + h.start(),
+                    token_end: lead + h.end() + 1,
+                    style: HeadingStyle::Inline,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+fn resolve_slashed(raw: &str, resolve: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    if !raw.contains('/') {
+        return None;
+    }
+    let parts: Vec<&str> = raw.split('/').map(str::trim).collect();
+    if parts.len() < 2 || parts.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+    let mut mapped = parts.iter().map(|part| resolve(part));
+    let first = mapped.next()??;
+    if mapped.all(|m| m.is_some()) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+fn match_decorated(
+    trimmed: &str,
+    lead: usize,
+    config: &Config,
+    resolve: &impl Fn(&str) -> Option<String>,
+) -> Option<HeadingMatch> {
+    let whole = |heading: String, style: HeadingStyle| HeadingMatch {
+        heading,
+        inline_content: None,
+        token_start: lead,
+        token_end: lead + trimmed.len(),
+        style,
+    };
+
+    if config.heading_style_enabled(HeadingStyle::Bold) {
+        if let Some(caps) = BOLD_RE.captures(trimmed) {
+            let inner = caps["h"].trim();
+            let rest = caps["rest"].trim_start();
+            let has_colon = inner.ends_with(':') || rest.starts_with(':');
+            let content = rest.trim_start_matches(':').trim();
+            if let Some(mapped) = resolve(inner.trim_end_matches(':')) {
+                if content.is_empty() {
+                    return Some(whole(mapped, HeadingStyle::Bold));
+                }
+                if has_colon {
+                    let token = trimmed[..trimmed.len() - content.len()].trim_end();
+                    return Some(HeadingMatch {
+                        heading: mapped,
+                        inline_content: Some(content.to_string()),
+                        token_start: lead,
+                        token_end: lead + token.len(),
+                        style: HeadingStyle::Bold,
+                    });
+                }
+            }
+        }
+    }
+
+    if config.heading_style_enabled(HeadingStyle::Dashed) {
+        if let Some(caps) = DASHED_RE.captures(trimmed) {
+            if let Some(mapped) = resolve(&caps["h"]) {
+                return Some(whole(mapped, HeadingStyle::Dashed));
+            }
+        }
+    }
+
+    for (style, pattern) in [
+        (HeadingStyle::Markdown, &MARKDOWN_RE),
+        (HeadingStyle::Numbered, &NUMBERED_RE),
+    ] {
+        if !config.heading_style_enabled(style) {
+            continue;
+        }
+        let Some(h) = pattern.captures(trimmed).and_then(|caps| caps.name("h")) else {
+            continue;
+        };
+        if let Some(mapped) = resolve(h.as_str()) {
+            return Some(whole(mapped, style));
+        }
+        if let Some(found) = match_plain(h.as_str(), lead + h.start(), resolve) {
+            return match found.inline_content {
+                Some(_) if style == HeadingStyle::Numbered => None,
+                Some(_) => Some(HeadingMatch {
+                    token_start: lead,
+                    style,
+                    ..found
+                }),
+                None => Some(whole(found.heading, style)),
+            };
         }
     }
 
     None
 }
 
+fn match_underlined(line: &str, config: &Config) -> Option<HeadingMatch> {
+    let trimmed = line.trim();
+    let lead = line.len() - line.trim_start().len();
+    canonicalize_heading(trimmed, config).map(|heading| HeadingMatch {
+        heading,
+        inline_content: None,
+        token_start: lead,
+        token_end: lead + trimmed.len(),
+        style: HeadingStyle::Underlined,
+    })
+}
+
+pub fn match_headings(line: &str, config: &Config) -> Vec<HeadingMatch> {
+    let Some(first) = match_heading(line, config) else {
+        return Vec::new();
+    };
+    if first.inline_content.is_none() {
+        return vec![first];
+    }
+    let after = &line[first.token_end..];
+    let rest_start = first.token_end + (after.len() - after.trim_start().len());
+    let rest = line[rest_start..].trim_end();
+
+    let tokens = inline_tokens(rest, config);
+    if tokens.is_empty() || !plausible_sequence(&first, &tokens, rest, config) {
+        return vec![first];
+    }
+
+    let mut found = vec![HeadingMatch {
+        inline_content: Some(rest[..tokens[0].start].trim().to_string()),
+        ..first
+    }];
+    for (idx, token) in tokens.iter().enumerate() {
+        let content_end = tokens.get(idx + 1).map(|t| t.start).unwrap_or(rest.len());
+        found.push(HeadingMatch {
+            heading: token.heading.clone(),
+            inline_content: Some(rest[token.end..content_end].trim().to_string()),
+            token_start: rest_start + token.start,
+            token_end: rest_start + token.end,
+            style: HeadingStyle::Inline,
+        });
+    }
+    found
+}
+
+struct InlineToken {
+    heading: String,
+    start: usize,
+    end: usize,
+}
+
+fn inline_tokens(rest: &str, config: &Config) -> Vec<InlineToken> {
+    let mut tokens = Vec::new();
+    for (colon, _) in rest.match_indices(':') {
+        let after = &rest[colon + 1..];
+        if !(after.is_empty() || after.starts_with(char::is_whitespace)) {
+            continue;
+        }
+        let before = &rest[..colon];
+        let word_starts: Vec<usize> = before
+            .char_indices()
+            .filter(|(idx, ch)| {
+                !ch.is_whitespace() && (*idx == 0 || before[..*idx].ends_with(char::is_whitespace))
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        for count in (1..=MAX_INLINE_HEADING_WORDS.min(word_starts.len())).rev() {
+            let start = word_starts[word_starts.len() - count];
+            if start == 0 || tokens.last().is_some_and(|t: &InlineToken| start < t.end) {
+                continue;
+            }
+            let candidate = &before[start..];
+            if !HEADING_TOKEN_RE.is_match(candidate) {
+                continue;
+            }
+            if candidate.len() == 1 && !candidate.chars().all(|c| c.is_ascii_uppercase()) {
+                continue;
+            }
+            if let Some(heading) = canonicalize_heading(candidate, config) {
+                tokens.push(InlineToken {
+                    heading,
+                    start,
+                    end: colon + 1,
+                });
+                break;
+            }
+        }
+    }
+    tokens
+}
+
+fn plausible_sequence(
+    first: &HeadingMatch,
+    tokens: &[InlineToken],
+    rest: &str,
+    config: &Config,
+) -> bool {
+    if rest[..tokens[0].start].trim().is_empty() {
+        return false;
+    }
+    if tokens
+        .windows(2)
+        .any(|pair| rest[pair[0].end..pair[1].start].trim().is_empty())
+    {
+        return false;
+    }
+
+    let sequence: Vec<String> = std::iter::once(first.heading.as_str())
+        .chain(tokens.iter().map(|t| t.heading.as_str()))
+        .map(util::normalize_heading_key)
+        .collect();
+    config.formats.names().iter().any(|format| {
+        let order: Vec<String> = config
+            .section_order(format)
+            .iter()
+            .map(|name| util::normalize_heading_key(name))
+            .collect();
+        let positions: Option<Vec<usize>> = sequence
+            .iter()
+            .map(|key| order.iter().position(|o| o == key))
+            .collect();
+        positions.is_some_and(|p| p.windows(2).all(|pair| pair[0] < pair[1]))
+    })
+}
+
 pub fn canonicalize_heading(raw: &str, config: &Config) -> Option<String> {
     if let Some(mapped) = config.resolve_heading_alias(raw) {
         return Some(mapped);
     }
-    let key = util::normalize_heading_key(raw);
-    HEADING_MAP.get(&key).cloned()
+    if let Some(custom) = sections::custom_heading(config, raw) {
+        return Some(custom);
+    }
+    sections::builtin_heading(raw).map(|name| name.as_str().to_string())
 }
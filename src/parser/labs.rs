@@ -0,0 +1,376 @@
+use crate::config::{Config, LabAnalyte};
+use crate::models::{LabFlag, LabResult, NoteChunk, StructuredNote};
+use crate::parser::spans::SectionCursor;
+use crate::util;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+const NUMBER: &str = r"\d+(?:\.\d+)?";
+const UNITS: &str = r"mmol/l|meq/l|mg/dl|g/dl|ng/ml|ng/l|pg/ml|k/ul|k/mm3|10\^3/ul|x10\^3/ul|10\^9/l|u/l|iu/l|sec|%";
+
+static BMP_LINE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"^(?P<na>{n})\s*\|\s*(?P<cl>{n})\s*\|\s*(?P<bun>{n})\s*/\s*(?P<k>{n})\s*\|\s*(?P<hco3>{n})\s*\|\s*(?P<cr>{n})\s*[<>]\s*(?P<glu>{n})$",
+        n = NUMBER
+    ))
+    .unwrap()
+});
+static BMP_ROW_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"^(?P<a>{n})\s*\|\s*(?P<b>{n})\s*\|\s*(?P<c>{n})(?:\s*[<>]\s*(?P<glu>{n}))?$",
+        n = NUMBER
+    ))
+    .unwrap()
+});
+static BMP_DIVIDER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"^[-+|_=\s]*-[-+|_=\s]*(?:[<>]\s*(?P<glu>{n}))?$",
+        n = NUMBER
+    ))
+    .unwrap()
+});
+static DOSE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^\s*(?:(?:meq|mmol|mg|mcg|g|gm|grams?|units?|ml|tabs?)(?:[^/A-Za-z0-9]|$)|(?:po|iv|im|sq|sc|sl|pr|ivpb)\b)",
+    )
+    .unwrap()
+});
+static CBC_LINE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"^(?P<wbc>{n})\s*>\s*(?P<hgb>{n})\s*/\s*(?P<hct>{n})\s*<\s*(?P<plt>{n})$",
+        n = NUMBER
+    ))
+    .unwrap()
+});
+
+struct Reading<'a> {
+    name: &'a str,
+    value: f64,
+    comparator: Option<&'a str>,
+    unit: Option<&'a str>,
+    mark: Option<&'a str>,
+    raw: &'a str,
+}
+
+struct Found {
+    result: LabResult,
+    needle: String,
+    order: (usize, usize),
+}
+
+pub struct LabDictionary {
+    analytes: Vec<(String, LabAnalyte)>,
+    lookup: HashMap<String, usize>,
+    pattern: Option<Regex>,
+}
+
+impl LabDictionary {
+    pub fn from_config(config: &Config) -> Self {
+        let analytes: Vec<(String, LabAnalyte)> =
+            config.labs.resolved_analytes().into_iter().collect();
+        let mut lookup = HashMap::new();
+        let mut names = Vec::new();
+        for (idx, (name, analyte)) in analytes.iter().enumerate() {
+            for synonym in std::iter::once(name).chain(&analyte.synonyms) {
+                let synonym = synonym.trim();
+                if synonym.is_empty() {
+                    continue;
+                }
+                lookup.entry(synonym.to_lowercase()).or_insert(idx);
+                names.push(synonym.to_string());
+            }
+        }
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        let pattern = (!names.is_empty()).then(|| {
+            let alternation = names
+                .iter()
+                .map(|name| regex::escape(name).replace(' ', r"\s+"))
+                .collect::<Vec<_>>()
+                .join("|");
+            Regex::new(&format!(
+                r"(?i)(?:^|[^A-Za-z0-9])(?P<name>{alternation})\s*(?:[:=]|\bwas\b|\bof\b)?\s*(?P<cmp>[<>]=?)?\s*(?P<value>{n})(?:\s*(?P<unit>{units}))?(?P<marked>\s*\(?(?P<mark>H|L)\)?(?:[^A-Za-z0-9(]|$))?",
+                n = NUMBER,
+                units = UNITS
+            ))
+            .unwrap()
+        });
+        Self {
+            analytes,
+            lookup,
+            pattern,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&(String, LabAnalyte)> {
+        let key = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.lookup
+            .get(&key.to_lowercase())
+            .map(|idx| &self.analytes[*idx])
+    }
+}
+
+pub fn note_labs(
+    note: &StructuredNote,
+    chunk: &NoteChunk,
+    config: &Config,
+    dictionary: &LabDictionary,
+) -> Vec<LabResult> {
+    let keys: Vec<String> = config
+        .labs
+        .sections
+        .iter()
+        .map(|name| util::normalize_heading_key(name))
+        .collect();
+    let mut results = Vec::new();
+    for section in note
+        .sections
+        .iter()
+        .filter(|s| keys.contains(&util::normalize_heading_key(&s.name)))
+    {
+        let mut cursor = SectionCursor::new(chunk, section);
+        for found in scan(&section.content, dictionary) {
+            let mut result = found.result;
+            result.section = section.name.clone();
+            result.span = cursor.as_mut().and_then(|c| c.find(&found.needle));
+            results.push(result);
+        }
+    }
+    results
+}
+
+pub fn extract_labs(content: &str, dictionary: &LabDictionary) -> Vec<LabResult> {
+    scan(content, dictionary)
+        .into_iter()
+        .map(|found| found.result)
+        .collect()
+}
+
+fn scan(content: &str, dictionary: &LabDictionary) -> Vec<Found> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut found = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        if let Some(consumed) = fishbone(&lines, idx, dictionary, &mut found) {
+            idx += consumed;
+            continue;
+        }
+        named_results(lines[idx], idx, dictionary, &mut found);
+        idx += 1;
+    }
+    found.sort_by_key(|f| f.order);
+    found
+}
+
+fn named_results(line: &str, line_idx: usize, dictionary: &LabDictionary, out: &mut Vec<Found>) {
+    let Some(pattern) = &dictionary.pattern else {
+        return;
+    };
+    for caps in pattern.captures_iter(line) {
+        let name = caps.name("name").unwrap();
+        let Some((analyte, spec)) = dictionary.get(name.as_str()) else {
+            continue;
+        };
+        let Ok(value) = caps["value"].parse::<f64>() else {
+            continue;
+        };
+        let reading_end = caps
+            .name("unit")
+            .unwrap_or_else(|| caps.name("value").unwrap())
+            .end();
+        if DOSE_RE.is_match(&line[reading_end..]) {
+            continue;
+        }
+        let end = match caps.name("marked") {
+            Some(marked) => marked.end(),
+            None => caps
+                .name("unit")
+                .unwrap_or_else(|| caps.name("value").unwrap())
+                .end(),
+        };
+        let raw = line[name.start()..end]
+            .trim_end_matches(|c: char| !c.is_alphanumeric() && c != ')' && c != '%')
+            .to_string();
+        let reading = Reading {
+            name: name.as_str(),
+            value,
+            comparator: caps.name("cmp").map(|m| m.as_str()),
+            unit: caps.name("unit").map(|m| m.as_str()),
+            mark: caps.name("mark").map(|m| m.as_str()),
+            raw: &raw,
+        };
+        out.push(Found {
+            result: lab_result(analyte, spec, reading),
+            needle: raw.clone(),
+            order: (line_idx, name.start()),
+        });
+    }
+}
+
+fn lab_result(analyte: &str, spec: &LabAnalyte, reading: Reading) -> LabResult {
+    let value = reading.value;
+    let comparable = match (reading.unit, &spec.unit) {
+        (Some(found), Some(expected)) => same_unit(found, expected),
+        _ => true,
+    };
+    let (low, high) = if comparable {
+        (spec.low, spec.high)
+    } else {
+        (None, None)
+    };
+    let flag = match (low, high) {
+        (Some(low), _) if value < low => Some(LabFlag::Low),
+        (_, Some(high)) if value > high => Some(LabFlag::High),
+        (None, None) => match reading.mark.map(|m| m.to_ascii_uppercase()).as_deref() {
+            Some("H") => Some(LabFlag::High),
+            Some("L") => Some(LabFlag::Low),
+            _ => None,
+        },
+        _ => Some(LabFlag::Normal),
+    };
+    LabResult {
+        analyte: analyte.to_string(),
+        name: reading
+            .name
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        value,
+        comparator: reading.comparator.map(str::to_string),
+        unit: reading
+            .unit
+            .map(str::to_string)
+            .or_else(|| spec.unit.clone()),
+        reference_low: low,
+        reference_high: high,
+        flag,
+        section: String::new(),
+        raw: reading.raw.trim().to_string(),
+        span: None,
+    }
+}
+
+fn fishbone(
+    lines: &[&str],
+    idx: usize,
+    dictionary: &LabDictionary,
+    out: &mut Vec<Found>,
+) -> Option<usize> {
+    let line = lines[idx].trim();
+    if let Some(caps) = BMP_LINE_RE.captures(line) {
+        let fields = [
+            ("na", "Sodium"),
+            ("cl", "Chloride"),
+            ("bun", "BUN"),
+            ("k", "Potassium"),
+            ("hco3", "Bicarbonate"),
+            ("cr", "Creatinine"),
+            ("glu", "Glucose"),
+        ];
+        push_fishbone(&caps, &fields, line, idx, dictionary, out);
+        return Some(1);
+    }
+    if let Some(caps) = CBC_LINE_RE.captures(line) {
+        let fields = [
+            ("wbc", "WBC"),
+            ("hgb", "Hemoglobin"),
+            ("hct", "Hematocrit"),
+            ("plt", "Platelets"),
+        ];
+        push_fishbone(&caps, &fields, line, idx, dictionary, out);
+        return Some(1);
+    }
+
+    let top = BMP_ROW_RE.captures(line)?;
+    let mut next = idx + 1;
+    let divider = lines
+        .get(next)
+        .and_then(|l| BMP_DIVIDER_RE.captures(l.trim()));
+    if divider.is_some() {
+        next += 1;
+    }
+    let bottom_line = lines.get(next)?.trim();
+    let bottom = BMP_ROW_RE.captures(bottom_line)?;
+    let raw = lines[idx..=next]
+        .iter()
+        .map(|l| l.trim())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut push_row = |caps: &Captures, fields: &[(&str, &str)], line_idx: usize| {
+        push_fishbone(caps, fields, &raw, line_idx, dictionary, out);
+    };
+    push_row(
+        &top,
+        &[
+            ("a", "Sodium"),
+            ("b", "Chloride"),
+            ("c", "BUN"),
+            ("glu", "Glucose"),
+        ],
+        idx,
+    );
+    if let Some(caps) = &divider {
+        push_row(caps, &[("glu", "Glucose")], idx + 1);
+    }
+    push_row(
+        &bottom,
+        &[
+            ("a", "Potassium"),
+            ("b", "Bicarbonate"),
+            ("c", "Creatinine"),
+            ("glu", "Glucose"),
+        ],
+        next,
+    );
+    Some(next - idx + 1)
+}
+
+fn push_fishbone(
+    caps: &Captures,
+    fields: &[(&str, &str)],
+    raw: &str,
+    line_idx: usize,
+    dictionary: &LabDictionary,
+    out: &mut Vec<Found>,
+) {
+    for (group, analyte) in fields {
+        let Some(m) = caps.name(group) else {
+            continue;
+        };
+        let Some((name, spec)) = dictionary.get(analyte) else {
+            continue;
+        };
+        let Ok(value) = m.as_str().parse::<f64>() else {
+            continue;
+        };
+        out.push(Found {
+            result: lab_result(
+                name,
+                spec,
+                Reading {
+                    name,
+                    value,
+                    comparator: None,
+                    unit: None,
+                    mark: None,
+                    raw,
+                },
+            ),
+            needle: m.as_str().to_string(),
+            order: (line_idx, m.start()),
+        });
+    }
+}
+
+fn same_unit(found: &str, expected: &str) -> bool {
+    fn canonical(unit: &str) -> String {
+        let unit = unit.to_lowercase().replace(' ', "");
+        match unit.as_str() {
+            "meq/l" => "mmol/l".to_string(),
+            "k/ul" | "k/mm3" | "x10^3/ul" | "10^9/l" => "10^3/ul".to_string(),
+            "iu/l" => "u/l".to_string(),
+            _ => unit,
+        }
+    }
+    canonical(found) == canonical(expected)
+}
@@ -0,0 +1,195 @@
+use crate::models::{Medication, MedicationStatus, NoteChunk, SectionName, StructuredNote};
+use crate::parser::spans::SectionCursor;
+use crate::util;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+const MAX_BARE_NAME_WORDS: usize = 4;
+
+static BULLET_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:[-*+]|\d{1,2}[.)])\s+").unwrap());
+static STRENGTH_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?P<strength>\d+(?:\.\d+)?(?:/\d+(?:\.\d+)?)?)\s*(?P<unit>mg/ml|mcg|mg|meq|units?|iu|ml|g|%|puffs?|tabs?|tablets?|caps?|capsules?|drops?)(?:[^A-Za-z]|$)",
+    )
+    .unwrap()
+});
+static ROUTE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?P<route>po|by mouth|oral|iv|im|sc|sq|subq|subcut|sl|pr|topical|inhaled|inh|neb|nebulized|transdermal|intranasal|ophthalmic)\b",
+    )
+    .unwrap()
+});
+static FREQUENCY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?P<frequency>once daily|twice daily|three times daily|four times daily|daily|bid|tid|qid|qd|qhs|qam|qpm|qod|every other day|q\s?\d+\s?(?:h|hr|hrs|hours)|every \d+ hours|nightly|at bedtime|weekly|monthly|once|stat)\b",
+    )
+    .unwrap()
+});
+static PRN_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bprn\b|\bas needed\b").unwrap());
+static STATUS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(?:^|\s|\()(?P<status>continue|continued|cont|resume|stop|stopped|discontinue|discontinued|d/c|hold|held|new|start|started|begin|initiate)(?:$|[\s:)\-,])",
+    )
+    .unwrap()
+});
+static NONE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:none|no medications?|no meds|no home (?:meds|medications)|n/?a)\.?$")
+        .unwrap()
+});
+static META_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:meds|medications?|list|reviewed|review|reconciled|reconciliation|see|per|unchanged|as above|as below|pending|not|unknown|verify|verified|updated)\b",
+    )
+    .unwrap()
+});
+
+pub fn note_medications(note: &StructuredNote, chunk: &NoteChunk) -> Vec<Medication> {
+    let key = util::normalize_heading_key(SectionName::Medications.as_str());
+    let mut found = Vec::new();
+    for section in note
+        .sections
+        .iter()
+        .filter(|s| util::normalize_heading_key(&s.name) == key)
+    {
+        let mut cursor = SectionCursor::new(chunk, section);
+        for mut medication in extract_medications(&section.content) {
+            medication.section = section.name.clone();
+            medication.span = cursor.as_mut().and_then(|c| c.find(&medication.raw));
+            found.push(medication);
+        }
+    }
+    found
+}
+
+pub fn extract_medications(content: &str) -> Vec<Medication> {
+    let mut found = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        let line = BULLET_RE
+            .find(line)
+            .map(|m| &line[m.end()..])
+            .unwrap_or(line);
+        if line.is_empty() || line.ends_with(':') {
+            continue;
+        }
+        for entry in split_entries(line) {
+            if let Some(medication) = parse_entry(entry) {
+                found.push(medication);
+            }
+        }
+    }
+    found
+}
+
+fn split_entries(line: &str) -> Vec<&str> {
+    let parts: Vec<&str> = line
+        .split(';')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    let mut entries = Vec::new();
+    for part in parts {
+        let pieces: Vec<&str> = part
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        let starts_with_name = pieces
+            .iter()
+            .all(|p| p.chars().next().is_some_and(|c| c.is_alphabetic()));
+        let all_dosed = pieces.iter().all(|p| STRENGTH_RE.is_match(p));
+        let none_dosed = pieces
+            .iter()
+            .all(|p| !p.chars().any(|c| c.is_ascii_digit()));
+        let separate = pieces.len() > 1 && starts_with_name && (all_dosed || none_dosed);
+        if separate {
+            entries.extend(pieces);
+        } else {
+            entries.push(part);
+        }
+    }
+    entries
+}
+
+fn parse_entry(entry: &str) -> Option<Medication> {
+    let raw = entry.trim().trim_end_matches('.').trim();
+    if raw.is_empty() || NONE_RE.is_match(raw) {
+        return None;
+    }
+
+    let strength = STRENGTH_RE.captures(raw);
+    let route = ROUTE_RE.captures(raw);
+    let frequency = FREQUENCY_RE.captures(raw);
+    let prn = PRN_RE.find(raw);
+    let status = STATUS_RE.captures(raw);
+
+    let mut name_end = raw.len();
+    for start in [
+        strength.as_ref().map(|c| c.get(0).unwrap().start()),
+        route.as_ref().map(|c| c.get(0).unwrap().start()),
+        frequency.as_ref().map(|c| c.get(0).unwrap().start()),
+        prn.map(|m| m.start()),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        name_end = name_end.min(start);
+    }
+    let mut name = raw[..name_end].to_string();
+    if let Some(status) = status.as_ref().and_then(|c| c.name("status")) {
+        if status.end() <= name_end {
+            name.replace_range(status.range(), " ");
+        }
+    }
+    let name = name
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '-' | ':' | ',' | '(' | ')'))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !name.chars().any(|c| c.is_alphabetic()) {
+        return None;
+    }
+    let described = strength.is_some()
+        || route.is_some()
+        || frequency.is_some()
+        || prn.is_some()
+        || status.is_some();
+    if !described
+        && (name.split_whitespace().count() > MAX_BARE_NAME_WORDS || META_RE.is_match(&name))
+    {
+        return None;
+    }
+
+    Some(Medication {
+        name,
+        strength: strength.as_ref().map(|c| c["strength"].to_string()),
+        unit: strength.as_ref().map(|c| c["unit"].to_lowercase()),
+        route: route.map(|c| medication_route(&c["route"])),
+        frequency: frequency.map(|c| c["frequency"].to_lowercase()),
+        prn: prn.is_some(),
+        status: status.map(|c| medication_status(&c["status"])),
+        section: String::new(),
+        raw: raw.to_string(),
+        span: None,
+    })
+}
+
+fn medication_route(raw: &str) -> String {
+    let lower = raw.to_lowercase();
+    match lower.as_str() {
+        "po" | "by mouth" | "oral" => "PO".to_string(),
+        "sc" | "sq" | "subq" | "subcut" => "SC".to_string(),
+        "inh" | "inhaled" => "inhaled".to_string(),
+        "neb" | "nebulized" => "nebulized".to_string(),
+        "iv" | "im" | "sl" | "pr" => lower.to_uppercase(),
+        _ => lower,
+    }
+}
+
+fn medication_status(raw: &str) -> MedicationStatus {
+    match raw.to_lowercase().as_str() {
+        "continue" | "continued" | "cont" | "resume" => MedicationStatus::Continue,
+        "new" | "start" | "started" | "begin" | "initiate" => MedicationStatus::New,
+        _ => MedicationStatus::Stop,
+    }
+}
@@ -1,24 +1,41 @@
+pub mod allergies;
 pub mod bundle;
+pub mod detect;
+pub mod header;
 pub mod headings;
+pub mod labs;
+pub mod medications;
 pub mod normalize;
+pub mod problems;
+pub mod ros;
 pub mod sectionize;
+pub mod spans;
+pub mod vitals;
 pub mod warnings;
 
 use crate::config::Config;
-use crate::models::{BundleMode, NoteFormat, ParseWarning, SectionCandidate, StructuredNote};
+use crate::models::{
+    BundleMode, FormatDetection, FormatSelection, NoteChunk, NoteFormat, ParseWarning,
+    SectionCandidate, StructuredNote,
+};
+use crate::pipeline::{Pipeline, PipelineOptions};
+use crate::sections::SectionRegistry;
 use crate::util;
 use anyhow::Result;
+use std::collections::HashSet;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct ParseOptions {
     pub apply_heuristics: bool,
+    pub fuzzy_headings: bool,
+    pub reproducible: bool,
 }
 
 pub fn split_bundle(
     text: &str,
     mode: BundleMode,
     config: &Config,
-) -> (Vec<String>, Vec<ParseWarning>) {
+) -> (Vec<NoteChunk>, Vec<ParseWarning>) {
     bundle::split_bundle(text, mode, config)
 }
 
@@ -30,11 +47,74 @@ pub fn extract_candidates(
 ) -> (Vec<SectionCandidate>, Vec<ParseWarning>) {
     let normalized = normalize::normalize_text(text);
     let lines: Vec<String> = normalized.lines().map(|l| l.to_string()).collect();
-    let headings = headings::scan_headings(&lines, config);
-    sectionize::extract_sections(&lines, &headings, format, config, options.apply_heuristics)
+    let registry = SectionRegistry::from_config(config);
+    let mut headings = headings::scan_headings(&lines, config);
+    if options.fuzzy_headings {
+        let taken: HashSet<usize> = headings.iter().map(|h| h.line_num).collect();
+        headings.extend(
+            headings::fuzzy_headings(&lines, config, &registry)
+                .into_iter()
+                .filter(|h| !taken.contains(&h.line_num)),
+        );
+        headings.sort_by_key(|h| h.line_num);
+    }
+    let (mut candidates, warnings) =
+        sectionize::extract_sections(&lines, &headings, format, config, options.apply_heuristics);
+    spans::attach_spans(text, &mut candidates);
+    registry.annotate(&mut candidates);
+    (candidates, warnings)
+}
+
+pub fn extract_chunk_candidates(
+    chunk: &NoteChunk,
+    format: NoteFormat,
+    config: &Config,
+    options: ParseOptions,
+) -> (Vec<SectionCandidate>, Vec<ParseWarning>) {
+    let (mut candidates, mut warnings) = extract_candidates(&chunk.text, format, config, options);
+    spans::shift_to_chunk(chunk, &mut candidates, &mut warnings);
+    (candidates, warnings)
+}
+
+pub fn detect_format(text: &str, config: &Config) -> (FormatDetection, Vec<ParseWarning>) {
+    detect::detect_format(text, config)
+}
+
+pub fn detect_chunk_format(
+    chunk: &NoteChunk,
+    config: &Config,
+) -> (FormatDetection, Vec<ParseWarning>) {
+    let (detection, mut warnings) = detect::detect_format(&chunk.text, config);
+    spans::shift_to_chunk(chunk, &mut [], &mut warnings);
+    (detection, warnings)
+}
+
+pub fn chunk_format(
+    chunk: &NoteChunk,
+    selection: &FormatSelection,
+    config: &Config,
+) -> (NoteFormat, Option<FormatDetection>, Vec<ParseWarning>) {
+    match selection {
+        FormatSelection::Fixed(format) => (format.clone(), None, Vec::new()),
+        FormatSelection::Auto => {
+            let (detection, warnings) = detect_chunk_format(chunk, config);
+            (detection.format.clone(), Some(detection), warnings)
+        }
+    }
+}
+
+pub fn note_id(source_file: Option<&str>, note_index: usize, text: &str) -> String {
+    let normalized = normalize::normalize_text(text);
+    let hash = util::stable_hash(&[
+        source_file.unwrap_or(""),
+        &note_index.to_string(),
+        normalized.trim(),
+    ]);
+    format!("note-{:016x}", hash)
 }
 
 pub fn build_note(
+    text: &str,
     candidates: Vec<SectionCandidate>,
     format: NoteFormat,
     source_file: Option<String>,
@@ -54,25 +134,93 @@ pub fn build_note(
         }
         sections.push(crate::models::Section {
             name: candidate.name,
+            label: candidate.label,
+            code: candidate.code,
             content: candidate.content.trim().to_string(),
             confidence: candidate.confidence,
+            span: candidate.span,
+            heading_span: candidate.heading_span,
+            heading_style: candidate.heading_style,
         });
     }
 
     StructuredNote {
-        id: format!("note-{}-{}", note_index, util::now_iso()),
+        id: note_id(source_file.as_deref(), note_index, text),
         format,
         source_file,
         note_index,
+        problems: problems::note_problems(&sections),
+        vitals: vitals::note_vitals(&sections),
         sections,
         warnings,
         metadata: crate::models::Metadata {
-            generated_at: util::now_iso(),
+            generated_at: util::timestamp(false),
             tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
         },
+        ..Default::default()
     }
 }
 
+pub fn build_chunk_note(
+    chunk: &NoteChunk,
+    candidates: Vec<SectionCandidate>,
+    format: NoteFormat,
+    source_file: Option<String>,
+    note_index: usize,
+    warnings: Vec<ParseWarning>,
+) -> StructuredNote {
+    let mut note = build_note(
+        &chunk.text,
+        candidates,
+        format,
+        source_file,
+        note_index,
+        warnings,
+    );
+    note.metadata.delimiter_captures = chunk.captures.clone();
+    note.encounter = chunk.encounter.clone();
+    note
+}
+
+pub fn attach_header(note: &mut StructuredNote, text: &str, config: &Config) {
+    note.header = header::extract_header(text, config);
+    if let Some(encounter) = note.encounter.as_mut() {
+        if encounter.date_of_service.is_none() {
+            encounter.date_of_service = note
+                .header
+                .get("encounter_date")
+                .and_then(|raw| bundle::service_date(raw));
+        }
+    }
+}
+
+pub struct Extractors {
+    labs: labs::LabDictionary,
+    ros: ros::RosMatcher,
+}
+
+impl Extractors {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            labs: labs::LabDictionary::from_config(config),
+            ros: ros::RosMatcher::from_config(&config.ros),
+        }
+    }
+}
+
+pub fn attach_extractions(
+    note: &mut StructuredNote,
+    chunk: &NoteChunk,
+    config: &Config,
+    extractors: &Extractors,
+) {
+    note.medications = medications::note_medications(note, chunk);
+    note.allergies = allergies::note_allergies(note, chunk);
+    note.labs = labs::note_labs(note, chunk, config, &extractors.labs);
+    note.ros = ros::note_ros(note, &extractors.ros);
+}
+
 pub fn parse_note(
     text: &str,
     format: NoteFormat,
@@ -81,35 +229,40 @@ pub fn parse_note(
     note_index: usize,
     options: ParseOptions,
 ) -> StructuredNote {
-    let (candidates, warnings) = extract_candidates(text, format, config, options);
-    build_note(candidates, format, source_file, note_index, warnings)
+    let (candidates, warnings) = extract_candidates(text, format.clone(), config, options);
+    let mut note = build_note(text, candidates, format, source_file, note_index, warnings);
+    attach_header(&mut note, text, config);
+    attach_extractions(
+        &mut note,
+        &NoteChunk::whole(text),
+        config,
+        &Extractors::from_config(config),
+    );
+    if options.reproducible {
+        note.metadata.generated_at = util::timestamp(true);
+    }
+    note
 }
 
 pub fn parse_notes(
     text: &str,
-    format: NoteFormat,
+    format: impl Into<FormatSelection>,
     config: &Config,
     source_file: Option<String>,
     note_offset: usize,
     options: ParseOptions,
-) -> Vec<StructuredNote> {
-    let (notes, bundle_warnings) = split_bundle(text, config.bundle.mode_default, config);
-    notes
-        .into_iter()
-        .enumerate()
-        .map(|(idx, note_text)| {
-            let (mut candidates, mut warnings) =
-                extract_candidates(&note_text, format, config, options);
-            warnings.extend(bundle_warnings.clone());
-            build_note(
-                candidates.drain(..).collect(),
-                format,
-                source_file.clone(),
-                note_offset + idx + 1,
-                warnings,
-            )
-        })
-        .collect()
+) -> Result<Vec<StructuredNote>> {
+    let pipeline_options = PipelineOptions {
+        format: format.into(),
+        bundle_mode: config.bundle.mode_default,
+        apply_heuristics: options.apply_heuristics,
+        strict: false,
+        reproducible: options.reproducible,
+        fuzzy_headings: options.fuzzy_headings,
+        note_offset,
+    };
+    let output = Pipeline::new(config.clone(), pipeline_options).parse_str(text, source_file)?;
+    Ok(output.notes)
 }
 
 pub fn write_notes_to_file(path: &std::path::Path, content: &str) -> Result<()> {
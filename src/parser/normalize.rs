@@ -4,8 +4,13 @@ pub fn normalize_text(input: &str) -> String {
     let mut lines = Vec::new();
     for line in text.lines() {
         let trimmed = line.trim_end();
-        let replaced = trimmed.replace('\u{2022}', "-").replace("* ", "- ");
-        lines.push(replaced);
+        let replaced = trimmed.replace('\u{2022}', "-");
+        let body = replaced.trim_start();
+        let indent = &replaced[..replaced.len() - body.len()];
+        match body.strip_prefix("* ") {
+            Some(item) => lines.push(format!("{}- {}", indent, item)),
+            None => lines.push(replaced),
+        }
     }
     lines.join("\n")
 }
@@ -0,0 +1,82 @@
+use crate::models::{Problem, Section, SectionName};
+use crate::sections;
+use crate::util;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static PROBLEM_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?:#\s*(?P<hash>\d{1,2})|(?P<num>\d{1,2})[.)]|#)[.):]?\s+(?P<title>.+?)(?:(?:\s+[-–]\s+|\s*:\s+)(?P<rest>.*))?$",
+    )
+    .unwrap()
+});
+static ITEM_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:[-*+]|[a-z][.)])\s+(?P<item>.+)$").unwrap());
+
+pub fn note_problems(sections: &[Section]) -> Vec<Problem> {
+    sections
+        .iter()
+        .find(|s| sections::combined_parts(&s.name).is_some())
+        .or_else(|| split_assessment_plan(sections))
+        .map(|section| extract_problems(&section.content))
+        .unwrap_or_default()
+}
+
+fn split_assessment_plan(sections: &[Section]) -> Option<&Section> {
+    let assessment = util::normalize_heading_key(SectionName::Assessment.as_str());
+    let plan = util::normalize_heading_key(SectionName::Plan.as_str());
+    sections.iter().find(|section| {
+        section.span.is_some()
+            && util::normalize_heading_key(&section.name) == assessment
+            && sections.iter().any(|other| {
+                other.span == section.span && util::normalize_heading_key(&other.name) == plan
+            })
+    })
+}
+
+pub fn extract_problems(content: &str) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = Vec::new();
+    let mut hash_marker = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let problem = PROBLEM_RE.captures(trimmed).filter(|_| {
+            let hashed = trimmed.starts_with('#');
+            *hash_marker.get_or_insert(hashed) == hashed
+        });
+        if let Some(caps) = problem {
+            let number = caps
+                .name("hash")
+                .or_else(|| caps.name("num"))
+                .and_then(|m| m.as_str().parse().ok());
+            let plan = caps
+                .name("rest")
+                .map(|m| plan_items(m.as_str()))
+                .unwrap_or_default();
+            problems.push(Problem {
+                number,
+                title: caps["title"].trim().to_string(),
+                plan,
+            });
+            continue;
+        }
+        let Some(current) = problems.last_mut() else {
+            continue;
+        };
+        let item = ITEM_RE
+            .captures(trimmed)
+            .map(|caps| caps["item"].to_string())
+            .unwrap_or_else(|| trimmed.to_string());
+        current.plan.extend(plan_items(&item));
+    }
+    problems
+}
+
+fn plan_items(text: &str) -> Vec<String> {
+    text.split(';')
+        .map(|item| item.trim().trim_end_matches('.').trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
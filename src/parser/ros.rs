@@ -0,0 +1,257 @@
+use crate::config::RosConfig;
+use crate::models::{FindingStatus, Ros, RosFinding, RosSystem, SectionName, StructuredNote};
+use crate::util;
+use once_cell::sync::Lazy;
+use regex::{Match, Regex};
+use std::collections::HashMap;
+
+const MAX_FINDING_WORDS: usize = 6;
+
+static BULLET_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:[-*+]|\d{1,2}[.)])\s+").unwrap());
+static SENTENCE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[.;](?:\s+|$)").unwrap());
+static ITEM_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i),|\s+(?:or|and|nor)\s+").unwrap());
+static SUBJECT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?:the\s+)?(?:patient|pt|he|she|they)\b(?:\s+(?:also|still|now|otherwise))?\s*",
+    )
+    .unwrap()
+});
+static ALL_NEGATIVE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)(?:^|\s*,\s*(?:(?:but|and)\s+)?|\s+(?:but|and)\s+)(?:all\s+other|otherwise|remainder|rest|complete|full|\d+[- ]?(?:point|system))\b.*\b(?:negative|neg|unremarkable|wnl)\b",
+    )
+    .unwrap()
+});
+static SYSTEM_NEGATIVE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?:negative|neg|wnl|normal|unremarkable|noncontributory|denies|none|no complaints)$",
+    )
+    .unwrap()
+});
+
+pub struct RosMatcher {
+    systems: Vec<String>,
+    lookup: HashMap<String, usize>,
+    label: Regex,
+    keyword: Regex,
+    negation: Regex,
+    post_negation: Regex,
+    uncertainty: Regex,
+    terminators: Regex,
+}
+
+impl RosMatcher {
+    pub fn from_config(config: &RosConfig) -> Self {
+        let mut systems = Vec::new();
+        let mut lookup = HashMap::new();
+        let mut labels = Vec::new();
+        let mut keywords = Vec::new();
+        for (idx, (name, def)) in config.resolved_systems().into_iter().enumerate() {
+            for alias in std::iter::once(&name).chain(&def.aliases) {
+                lookup.insert(label_key(alias), idx);
+                labels.push(alias.clone());
+            }
+            for finding in &def.findings {
+                lookup.entry(label_key(finding)).or_insert(idx);
+                keywords.push(finding.clone());
+            }
+            systems.push(name);
+        }
+        Self {
+            systems,
+            lookup,
+            label: phrase_regex(r"^\s*(?P<label>", &labels, r")\s*[:\-–]\s*"),
+            keyword: phrase_regex("", &keywords, ""),
+            negation: phrase_regex("", &config.negation, ""),
+            post_negation: phrase_regex(r"[\s:\-]*", &config.post_negation, r"\s*$"),
+            uncertainty: phrase_regex("", &config.uncertainty, ""),
+            terminators: phrase_regex(r"\s*", &config.terminators, r"\s*"),
+        }
+    }
+
+    fn system_for(&self, text: &str) -> Option<usize> {
+        self.keyword
+            .find(text)
+            .and_then(|m| self.lookup.get(&label_key(m.as_str())).copied())
+    }
+
+    fn trigger<'t>(&self, text: &'t str) -> Option<(Match<'t>, FindingStatus)> {
+        let negated = self.negation.find(text).map(|m| (m, FindingStatus::Absent));
+        let uncertain = self
+            .uncertainty
+            .find(text)
+            .map(|m| (m, FindingStatus::Uncertain));
+        match (negated, uncertain) {
+            (Some(n), Some(u)) => Some(if u.0.start() < n.0.start() { u } else { n }),
+            (n, u) => n.or(u),
+        }
+    }
+}
+
+pub fn note_ros(note: &StructuredNote, matcher: &RosMatcher) -> Option<Ros> {
+    let key = util::normalize_heading_key(SectionName::Ros.as_str());
+    let mut found: Option<Ros> = None;
+    for section in note
+        .sections
+        .iter()
+        .filter(|s| util::normalize_heading_key(&s.name) == key)
+    {
+        let parsed = extract_ros(&section.content, matcher);
+        let merged = found.get_or_insert_with(Ros::default);
+        merged.all_other_negative |= parsed.all_other_negative;
+        for system in parsed.systems {
+            let target = system_mut(merged, &system.system);
+            target.negative |= system.negative;
+            target.findings.extend(system.findings);
+        }
+    }
+    found
+}
+
+pub fn extract_ros(content: &str, matcher: &RosMatcher) -> Ros {
+    let mut ros = Ros::default();
+    for line in content.lines() {
+        let line = line.trim();
+        let line = BULLET_RE
+            .find(line)
+            .map(|m| &line[m.end()..])
+            .unwrap_or(line);
+        let mut label = None;
+        for sentence in SENTENCE_RE.split(line) {
+            let mut rest = sentence.trim();
+            let mut labeled = false;
+            if let Some(caps) = matcher.label.captures(rest) {
+                if let Some(idx) = matcher.lookup.get(&label_key(&caps["label"])) {
+                    label = Some(*idx);
+                    labeled = true;
+                    rest = rest[caps.get(0).unwrap().end()..].trim();
+                }
+            }
+            if let Some(found) = ALL_NEGATIVE_RE.find(rest) {
+                ros.all_other_negative = true;
+                rest = rest[..found.start()].trim();
+                if rest.is_empty() && label.is_none() {
+                    continue;
+                }
+            }
+            if let Some(idx) = label {
+                let system = system_mut(&mut ros, &matcher.systems[idx]);
+                if SYSTEM_NEGATIVE_RE.is_match(rest) {
+                    system.negative = true;
+                    continue;
+                }
+            }
+            parse_findings(rest, label, labeled, matcher, &mut ros);
+        }
+    }
+    ros
+}
+
+fn parse_findings(
+    text: &str,
+    label: Option<usize>,
+    labeled: bool,
+    matcher: &RosMatcher,
+    ros: &mut Ros,
+) {
+    for clause in matcher.terminators.split(text) {
+        let mut status = FindingStatus::Present;
+        for item in ITEM_RE.split(clause) {
+            let mut item = item.trim();
+            if let Some(subject) = SUBJECT_RE.find(item) {
+                item = &item[subject.end()..];
+            }
+            if let Some((trigger, trigger_status)) = matcher.trigger(item) {
+                status = trigger_status;
+                let after = item[trigger.end()..].trim();
+                let attached = !item[..trigger.start()].is_empty()
+                    && !item[..trigger.start()].ends_with(char::is_whitespace);
+                item = if after.is_empty() || attached {
+                    &item[..trigger.start()]
+                } else {
+                    after
+                };
+            }
+            let mut item_status = status;
+            if let Some(post) = matcher.post_negation.find(item) {
+                item_status = FindingStatus::Absent;
+                item = &item[..post.start()];
+            }
+            let finding = clean_finding(item);
+            let words = finding.split_whitespace().count();
+            if words == 0 || words > MAX_FINDING_WORDS {
+                continue;
+            }
+            let system = label
+                .filter(|_| labeled)
+                .or_else(|| matcher.system_for(&finding))
+                .or(label)
+                .map(|idx| matcher.systems[idx].as_str())
+                .unwrap_or(RosSystem::UNSPECIFIED);
+            system_mut(ros, system).findings.push(RosFinding {
+                finding,
+                status: item_status,
+            });
+        }
+    }
+}
+
+fn clean_finding(text: &str) -> String {
+    let text = text.trim_matches(|c: char| {
+        c.is_whitespace() || matches!(c, '+' | ':' | '-' | ',' | '(' | ')')
+    });
+    let text = text
+        .strip_prefix("any ")
+        .or_else(|| text.strip_prefix("Any "))
+        .unwrap_or(text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn system_mut<'a>(ros: &'a mut Ros, name: &str) -> &'a mut RosSystem {
+    let idx = match ros.systems.iter().position(|s| s.system == name) {
+        Some(idx) => idx,
+        None => {
+            ros.systems.push(RosSystem {
+                system: name.to_string(),
+                ..Default::default()
+            });
+            ros.systems.len() - 1
+        }
+    };
+    &mut ros.systems[idx]
+}
+
+fn label_key(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn phrase_regex(prefix: &str, phrases: &[String], suffix: &str) -> Regex {
+    let mut phrases: Vec<&str> = phrases
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    phrases.sort_by_key(|p| std::cmp::Reverse(p.len()));
+    let alternation = if phrases.is_empty() {
+        r"[^\s\S]".to_string()
+    } else {
+        phrases
+            .iter()
+            .map(|phrase| {
+                let starts = phrase.starts_with(|c: char| c.is_alphanumeric());
+                let ends = phrase.ends_with(|c: char| c.is_alphanumeric());
+                format!(
+                    "{}{}{}",
+                    if starts { r"\b" } else { "" },
+                    regex::escape(phrase).replace(' ', r"\s+"),
+                    if ends { r"\b" } else { "" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("|")
+    };
+    Regex::new(&format!("(?i){}(?:{}){}", prefix, alternation, suffix)).unwrap()
+}
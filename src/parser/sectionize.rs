@@ -1,10 +1,18 @@
-use crate::config::Config;
-use crate::models::{HeadingLine, NoteFormat, ParseWarning, SectionCandidate, WarningSeverity};
+use crate::config::{AssessmentPlanMode, Config, UnmappedPolicy};
+use crate::models::{
+    HeadingLine, HeadingStyle, NoteFormat, ParseWarning, SectionCandidate, WarningSeverity,
+};
 use crate::parser::headings;
 use crate::parser::warnings;
+use crate::sections;
 use crate::util;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::collections::HashSet;
+
+const HEADING_CONFIDENCE: f32 = 0.85;
+const FALLBACK_CONFIDENCE: f32 = 0.6;
+const FUZZY_CONFIDENCE: f32 = 0.7;
 
 static FALLBACK_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?i)^(?P<h>[A-Za-z /&.-]{2,40})\s*[:\-]\s*(?P<rest>.+)$").unwrap());
@@ -18,109 +26,224 @@ pub fn extract_sections(
 ) -> (Vec<SectionCandidate>, Vec<ParseWarning>) {
     let mut warnings_list = Vec::new();
     let mut headings = headings_found.to_vec();
-    let mut used_fallback = false;
 
-    if headings.is_empty() {
-        if apply_heuristics {
-            headings = fallback_headings(lines, config);
-            if !headings.is_empty() {
-                used_fallback = true;
-                warnings_list.push(warnings::warning(
-                    "fallback_heuristics",
-                    "Fallback heuristics applied to find headings".to_string(),
-                    1,
-                    lines.len().max(1),
-                    WarningSeverity::Info,
-                ));
-            }
-        }
-        if headings.is_empty() {
+    if apply_heuristics && (headings.is_empty() || config.fallback_per_line) {
+        let taken: HashSet<usize> = headings.iter().map(|h| h.line_num).collect();
+        let extra: Vec<HeadingLine> = fallback_headings(lines, config)
+            .into_iter()
+            .filter(|h| !taken.contains(&h.line_num))
+            .collect();
+        if !extra.is_empty() {
             warnings_list.push(warnings::warning(
-                "no_headings",
-                "No headings detected; content grouped as Narrative".to_string(),
+                "fallback_heuristics",
+                "Fallback heuristics applied to find headings".to_string(),
                 1,
                 lines.len().max(1),
-                WarningSeverity::Warning,
+                WarningSeverity::Info,
             ));
-            let content = lines.join("\n").trim().to_string();
-            let candidate = SectionCandidate {
-                name: "Narrative".to_string(),
-                raw_heading: "Narrative".to_string(),
-                content,
-                start_line: 1,
-                end_line: lines.len().max(1),
-                confidence: 0.4,
-            };
-            return (vec![candidate], warnings_list);
+        }
+        for heading in extra {
+            warnings_list.push(warnings::warning(
+                "fallback_heading_used",
+                format!(
+                    "Line {} treated as '{}' heading by fallback heuristics",
+                    heading.line_num, heading.heading
+                ),
+                heading.line_num,
+                heading.line_num,
+                WarningSeverity::Info,
+            ));
+            headings.push(heading);
         }
     }
 
+    if headings.is_empty() {
+        warnings_list.push(warnings::warning(
+            "no_headings",
+            "No headings detected; content grouped as Narrative".to_string(),
+            1,
+            lines.len().max(1),
+            WarningSeverity::Warning,
+        ));
+        let content = lines.join("\n").trim().to_string();
+        let candidate = SectionCandidate {
+            name: "Narrative".to_string(),
+            label: None,
+            code: None,
+            raw_heading: "Narrative".to_string(),
+            content,
+            start_line: 1,
+            end_line: lines.len().max(1),
+            confidence: 0.4,
+            span: None,
+            heading_span: None,
+            heading_style: None,
+            heading_columns: None,
+        };
+        return (vec![candidate], warnings_list);
+    }
+
     headings.sort_by_key(|h| h.line_num);
 
-    let section_order = config.section_order(format);
+    let section_order = config.section_order(&format);
     let mut candidates = Vec::new();
 
     for (idx, heading) in headings.iter().enumerate() {
         let start_line = heading.line_num;
-        let end_line = if idx + 1 < headings.len() {
-            headings[idx + 1].line_num.saturating_sub(1)
-        } else {
-            lines.len().max(1)
+        let end_line = match headings.get(idx + 1) {
+            Some(next) if next.line_num == start_line => start_line,
+            Some(next) => next.line_num.saturating_sub(1),
+            None => lines.len().max(1),
         };
 
         let mut content_lines = Vec::new();
         if let Some(inline) = &heading.inline_content {
             content_lines.push(inline.clone());
         }
-        let content_start = heading.line_num + 1;
+        let content_start = if heading.style == HeadingStyle::Underlined {
+            heading.line_num + 2
+        } else {
+            heading.line_num + 1
+        };
         for line_idx in content_start..=end_line {
             if let Some(line) = lines.get(line_idx - 1) {
                 content_lines.push(line.clone());
             }
         }
 
-        let (name, mapped) = map_heading(&heading.heading, &section_order);
-        if !mapped {
+        if let Some(matched) = &heading.fuzzy {
+            let raw = heading
+                .raw
+                .get(heading.token_start..heading.token_end)
+                .unwrap_or(&heading.raw)
+                .trim()
+                .trim_end_matches(':');
             warnings_list.push(warnings::warning(
-                "unmapped_heading",
-                format!("Heading '{}' not in target format", heading.heading),
+                "fuzzy_heading",
+                format!(
+                    "Heading '{}' fuzzy-matched to '{}' (alias '{}', edit distance {})",
+                    raw, heading.heading, matched.alias, matched.distance
+                ),
+                start_line,
                 start_line,
-                end_line,
                 WarningSeverity::Info,
             ));
         }
 
-        let confidence = if used_fallback { 0.6 } else { 0.85 };
+        let confidence = if heading.fallback {
+            FALLBACK_CONFIDENCE
+        } else if heading.fuzzy.is_some() {
+            FUZZY_CONFIDENCE
+        } else {
+            HEADING_CONFIDENCE
+        };
         let candidate = SectionCandidate {
-            name,
+            name: heading.heading.clone(),
+            label: None,
+            code: None,
             raw_heading: heading.heading.clone(),
             content: content_lines.join("\n").trim().to_string(),
             start_line,
             end_line,
             confidence,
+            span: None,
+            heading_span: None,
+            heading_style: Some(heading.style),
+            heading_columns: Some((heading.token_start, heading.token_end)),
         };
-        candidates.push(candidate);
+
+        if let Some(parts) = sections::combined_parts(&heading.heading) {
+            let mapped: Vec<String> = parts
+                .iter()
+                .map(|part| map_heading(part.as_str(), &section_order))
+                .filter(|(_, mapped)| *mapped)
+                .map(|(name, _)| name)
+                .collect();
+            if !mapped.is_empty() {
+                match config.assessment_plan {
+                    AssessmentPlanMode::Split => {
+                        warnings_list.push(warnings::warning(
+                            "combined_section",
+                            format!(
+                                "Heading '{}' emitted as {}",
+                                heading.heading,
+                                mapped.join(" and ")
+                            ),
+                            start_line,
+                            end_line,
+                            WarningSeverity::Info,
+                        ));
+                        for name in mapped {
+                            candidates.push(SectionCandidate {
+                                name,
+                                ..candidate.clone()
+                            });
+                        }
+                    }
+                    AssessmentPlanMode::Combined => candidates.push(candidate),
+                }
+                continue;
+            }
+        }
+
+        let (name, mapped) = map_heading(&heading.heading, &section_order);
+        if !mapped {
+            let action = match config.unmapped_sections {
+                UnmappedPolicy::Narrative => "grouped as Narrative",
+                UnmappedPolicy::Keep => "kept as its own section",
+                UnmappedPolicy::Drop => "dropped",
+            };
+            warnings_list.push(warnings::warning(
+                "unmapped_heading",
+                format!(
+                    "Heading '{}' not in target format; {}",
+                    heading.heading, action
+                ),
+                start_line,
+                end_line,
+                WarningSeverity::Info,
+            ));
+            match config.unmapped_sections {
+                UnmappedPolicy::Narrative => {
+                    candidates.push(SectionCandidate { name, ..candidate })
+                }
+                UnmappedPolicy::Keep => candidates.push(candidate),
+                UnmappedPolicy::Drop => {}
+            }
+            continue;
+        }
+        candidates.push(SectionCandidate { name, ..candidate });
     }
 
     let mut ordered = Vec::new();
+    let mut order_keys = Vec::new();
     for name in section_order {
         let key = util::normalize_heading_key(&name);
         for candidate in &candidates {
-            if util::normalize_heading_key(&candidate.name) == key {
+            if order_key(&candidate.name) == key {
                 ordered.push(candidate.clone());
             }
         }
+        order_keys.push(key);
     }
-    for candidate in candidates {
-        if util::normalize_heading_key(&candidate.name) == util::normalize_heading_key("Narrative")
-        {
-            ordered.push(candidate);
-        }
-    }
+    let narrative_key = util::normalize_heading_key("Narrative");
+    let (narrative, kept): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .filter(|c| !order_keys.contains(&order_key(&c.name)))
+        .partition(|c| util::normalize_heading_key(&c.name) == narrative_key);
+    ordered.extend(kept);
+    ordered.extend(narrative);
 
     (ordered, warnings_list)
 }
 
+fn order_key(name: &str) -> String {
+    match sections::combined_parts(name) {
+        Some([first, _]) => util::normalize_heading_key(first.as_str()),
+        None => util::normalize_heading_key(name),
+    }
+}
+
 fn map_heading(heading: &str, section_order: &[String]) -> (String, bool) {
     let heading_key = util::normalize_heading_key(heading);
     for name in section_order {
@@ -134,15 +257,22 @@ fn map_heading(heading: &str, section_order: &[String]) -> (String, bool) {
 fn fallback_headings(lines: &[String], config: &Config) -> Vec<HeadingLine> {
     let mut headings = Vec::new();
     for (idx, line) in lines.iter().enumerate() {
+        let lead = line.len() - line.trim_start().len();
         if let Some(caps) = FALLBACK_RE.captures(line.trim()) {
             let raw = caps.name("h").map(|m| m.as_str()).unwrap_or("");
             let rest = caps.name("rest").map(|m| m.as_str()).unwrap_or("");
+            let token_end = caps.name("rest").map(|m| m.start()).unwrap_or(raw.len());
             if let Some(mapped) = headings::canonicalize_heading(raw, config) {
                 headings.push(HeadingLine {
                     line_num: idx + 1,
                     raw: line.clone(),
                     heading: mapped,
                     inline_content: Some(rest.trim().to_string()),
+                    token_start: lead,
+                    token_end: lead + line.trim()[..token_end].trim_end().len(),
+                    fallback: true,
+                    fuzzy: None,
+                    style: HeadingStyle::Fallback,
                 });
             }
         }
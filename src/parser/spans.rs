@@ -0,0 +1,224 @@
+use crate::models::{NoteChunk, ParseWarning, Section, SectionCandidate, SourceSpan};
+
+#[derive(Debug, Clone, Copy)]
+pub struct LineInfo {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    text: &'a str,
+    lines: Vec<LineInfo>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let mut lines = Vec::new();
+        let mut byte_start = 0;
+        let mut char_start = 0;
+        let mut char_pos = 0;
+        let mut chars = text.char_indices().peekable();
+        while let Some((pos, ch)) = chars.next() {
+            if ch == '\n' || ch == '\r' {
+                lines.push(LineInfo {
+                    byte_start,
+                    byte_end: pos,
+                    char_start,
+                    char_end: char_pos,
+                });
+                let mut next_byte = pos + 1;
+                char_pos += 1;
+                if ch == '\r' {
+                    if let Some((_, '\n')) = chars.peek() {
+                        chars.next();
+                        next_byte += 1;
+                        char_pos += 1;
+                    }
+                }
+                byte_start = next_byte;
+                char_start = char_pos;
+            } else {
+                char_pos += 1;
+            }
+        }
+        if byte_start < text.len() {
+            lines.push(LineInfo {
+                byte_start,
+                byte_end: text.len(),
+                char_start,
+                char_end: char_pos,
+            });
+        }
+        Self { text, lines }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn line(&self, line_num: usize) -> Option<&LineInfo> {
+        line_num.checked_sub(1).and_then(|idx| self.lines.get(idx))
+    }
+
+    pub fn line_text(&self, line_num: usize) -> &'a str {
+        self.line(line_num)
+            .map(|info| &self.text[info.byte_start..info.byte_end])
+            .unwrap_or("")
+    }
+
+    pub fn span_lines(&self, line_start: usize, line_end: usize) -> Option<SourceSpan> {
+        let first = self.line(line_start)?;
+        let last = self.line(line_end.max(line_start)).unwrap_or(first);
+        Some(SourceSpan {
+            line_start,
+            line_end: line_end.max(line_start),
+            byte_start: first.byte_start,
+            byte_end: last.byte_end,
+            char_start: first.char_start,
+            char_end: last.char_end,
+        })
+    }
+
+    pub fn span_bytes(&self, byte_start: usize, byte_end: usize) -> Option<SourceSpan> {
+        let position = |byte: usize| {
+            let idx = self
+                .lines
+                .iter()
+                .rposition(|info| info.byte_start <= byte)?;
+            let info = &self.lines[idx];
+            let chars = self.text.get(info.byte_start..byte)?.chars().count();
+            Some((idx + 1, info.char_start + chars))
+        };
+        let (line_start, char_start) = position(byte_start)?;
+        let (line_end, char_end) = position(byte_end)?;
+        Some(SourceSpan {
+            line_start,
+            line_end,
+            byte_start,
+            byte_end,
+            char_start,
+            char_end,
+        })
+    }
+
+    pub fn span_columns(
+        &self,
+        line_num: usize,
+        normalized_start: usize,
+        normalized_end: usize,
+    ) -> Option<SourceSpan> {
+        let info = self.line(line_num)?;
+        let line = self.line_text(line_num);
+        let (byte_start, char_start) = original_column(line, normalized_start);
+        let (byte_end, char_end) = original_column(line, normalized_end);
+        Some(SourceSpan {
+            line_start: line_num,
+            line_end: line_num,
+            byte_start: info.byte_start + byte_start,
+            byte_end: info.byte_start + byte_end,
+            char_start: info.char_start + char_start,
+            char_end: info.char_start + char_end,
+        })
+    }
+}
+
+fn original_column(line: &str, normalized_col: usize) -> (usize, usize) {
+    let mut normalized = 0;
+    for (char_idx, (byte_idx, ch)) in line.char_indices().enumerate() {
+        if normalized >= normalized_col {
+            return (byte_idx, char_idx);
+        }
+        normalized += match ch {
+            '\t' | '\u{2022}' => 1,
+            other => other.len_utf8(),
+        };
+    }
+    (line.len(), line.chars().count())
+}
+
+pub fn attach_spans(text: &str, candidates: &mut [SectionCandidate]) {
+    let index = LineIndex::new(text);
+    for candidate in candidates.iter_mut() {
+        candidate.span = index.span_lines(candidate.start_line, candidate.end_line);
+        candidate.heading_span = candidate
+            .heading_columns
+            .and_then(|(start, end)| index.span_columns(candidate.start_line, start, end));
+    }
+}
+
+pub fn shift_span(span: &mut SourceSpan, chunk: &NoteChunk) {
+    span.line_start += chunk.line_offset;
+    span.line_end += chunk.line_offset;
+    span.byte_start += chunk.byte_offset;
+    span.byte_end += chunk.byte_offset;
+    span.char_start += chunk.char_offset;
+    span.char_end += chunk.char_offset;
+}
+
+pub struct SectionCursor<'a> {
+    chunk: &'a NoteChunk,
+    index: LineIndex<'a>,
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> SectionCursor<'a> {
+    pub fn new(chunk: &'a NoteChunk, section: &Section) -> Option<Self> {
+        let span = section.span?;
+        let start = span.byte_start.checked_sub(chunk.byte_offset)?;
+        let end = span.byte_end.checked_sub(chunk.byte_offset)?;
+        if end > chunk.text.len() || start > end {
+            return None;
+        }
+        Some(Self {
+            chunk,
+            index: LineIndex::new(&chunk.text),
+            pos: start,
+            end,
+        })
+    }
+
+    pub fn find(&mut self, needle: &str) -> Option<SourceSpan> {
+        if needle.is_empty() {
+            return None;
+        }
+        let found = self.chunk.text.get(self.pos..self.end)?.find(needle)?;
+        let byte_start = self.pos + found;
+        let byte_end = byte_start + needle.len();
+        self.pos = byte_end;
+        let mut span = self.index.span_bytes(byte_start, byte_end)?;
+        shift_span(&mut span, self.chunk);
+        Some(span)
+    }
+}
+
+pub fn shift_to_chunk(
+    chunk: &NoteChunk,
+    candidates: &mut [SectionCandidate],
+    warnings: &mut [ParseWarning],
+) {
+    if chunk.line_offset == 0 && chunk.byte_offset == 0 {
+        return;
+    }
+    for candidate in candidates.iter_mut() {
+        candidate.start_line += chunk.line_offset;
+        candidate.end_line += chunk.line_offset;
+        if let Some(span) = candidate.span.as_mut() {
+            shift_span(span, chunk);
+        }
+        if let Some(span) = candidate.heading_span.as_mut() {
+            shift_span(span, chunk);
+        }
+    }
+    for warning in warnings.iter_mut() {
+        warning.line_start += chunk.line_offset;
+        warning.line_end += chunk.line_offset;
+    }
+}
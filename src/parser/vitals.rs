@@ -0,0 +1,293 @@
+use crate::models::{Section, SectionName, Vitals};
+use crate::util;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+const LB_TO_KG: f64 = 0.453_592_37;
+const IN_TO_CM: f64 = 2.54;
+
+static BP_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:bp|blood pressure)\s*:?\s*(?P<sys>\d{2,3})\s*/\s*(?P<dia>\d{2,3})\b")
+        .unwrap()
+});
+static HR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:hr|heart rate|pulse|p\s*[:=])\s*:?\s*(?P<value>\d{1,4})\b(?:\s*(?:bpm|/min))?",
+    )
+    .unwrap()
+});
+static HR_SHORT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bp\s+(?P<value>\d{2,3})\b").unwrap());
+static RR_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:rr|resp(?:iratory)? rate|resp|r\s*[:=])\s*:?\s*(?P<value>\d{1,3})\b")
+        .unwrap()
+});
+static RR_SHORT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\br\s+(?P<value>\d{1,2})\b").unwrap());
+static TEMP_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:temperature|temp|tmax|t\s*[:=])\s*:?\s*(?P<value>\d{2,3}(?:\.\d+)?)\s*°?\s*(?P<unit>c|f)?\b",
+    )
+    .unwrap()
+});
+static TEMP_SHORT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\bt\s*(?P<value>\d{2,3}(?:\.\d+)?)\s*°?\s*(?P<unit>c|f)?\b").unwrap()
+});
+static CONTEXT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:vitals?|vs|bp|blood pressure|hr|heart rate|pulse|rr|resp|temp|temperature|tmax|spo2|sao2|sats?)\b")
+        .unwrap()
+});
+static SPO2_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)\b(?:spo2|sao2|o2 sat(?:uration)?|sats?|saturation|pulse ox|o2)\s*:?\s*(?P<value>\d{2,3})\s*%?(?:\s*(?:on\s+)?(?P<o2>room air|ra|\d+(?:\.\d+)?\s*(?:l|lpm|l/min)(?:\s*(?:nc|nasal cannula|nrb|fm|face mask|hfnc))?|nasal cannula|nc|non-rebreather|nrb|bipap|cpap|hfnc|high flow))?\b",
+    )
+    .unwrap()
+});
+static WEIGHT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:weight|wt)\s*:?\s*(?P<value>\d+(?:\.\d+)?)\s*(?P<unit>kg|lbs?|pounds)?")
+        .unwrap()
+});
+static HEIGHT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)\b(?:height|ht)\s*:?\s*(?:(?P<feet>\d)\s*'\s*(?P<inches>\d{1,2})\s*(?:"|'')?|(?P<value>\d+(?:\.\d+)?)\s*(?P<unit>cm|m|in|inches)?)"#,
+    )
+    .unwrap()
+});
+
+pub fn is_vitals_section(name: &str) -> bool {
+    let key = util::normalize_heading_key(name);
+    [SectionName::Objective, SectionName::PhysicalExam]
+        .iter()
+        .any(|section| util::normalize_heading_key(section.as_str()) == key)
+}
+
+pub fn note_vitals(sections: &[Section]) -> Option<Vitals> {
+    let mut found = Vitals::default();
+    for section in sections.iter().filter(|s| is_vitals_section(&s.name)) {
+        found.merge(extract_vitals(&section.content));
+    }
+    (!found.is_empty()).then_some(found)
+}
+
+pub fn extract_vitals(content: &str) -> Vitals {
+    let mut vitals = Vitals::default();
+    for reading in readings(content) {
+        vitals.merge(reading);
+    }
+    vitals
+}
+
+pub fn section_implausible(content: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for message in readings(content).iter().flat_map(implausible) {
+        if !found.contains(&message) {
+            found.push(message);
+        }
+    }
+    found
+}
+
+fn readings(content: &str) -> Vec<Vitals> {
+    let mut found: Vec<(usize, Vitals)> = Vec::new();
+    for caps in BP_RE.captures_iter(content) {
+        found.push((
+            caps.get(0).unwrap().start(),
+            Vitals {
+                bp_systolic: caps["sys"].parse().ok(),
+                bp_diastolic: caps["dia"].parse().ok(),
+                ..Default::default()
+            },
+        ));
+    }
+    for (start, caps) in matches(content, &HR_RE, &HR_SHORT_RE, |_| false) {
+        found.push((
+            start,
+            Vitals {
+                heart_rate: caps["value"].parse().ok(),
+                ..Default::default()
+            },
+        ));
+    }
+    for (start, caps) in matches(content, &RR_RE, &RR_SHORT_RE, |_| false) {
+        found.push((
+            start,
+            Vitals {
+                respiratory_rate: caps["value"].parse().ok(),
+                ..Default::default()
+            },
+        ));
+    }
+    let written_as_temperature =
+        |caps: &Captures| caps["value"].contains('.') || caps.name("unit").is_some();
+    for (start, caps) in matches(content, &TEMP_RE, &TEMP_SHORT_RE, written_as_temperature) {
+        found.push((
+            start,
+            Vitals {
+                temperature_c: temperature_c(&caps),
+                ..Default::default()
+            },
+        ));
+    }
+    for caps in SPO2_RE.captures_iter(content) {
+        found.push((
+            caps.get(0).unwrap().start(),
+            Vitals {
+                spo2: caps["value"].parse().ok(),
+                o2_delivery: caps.name("o2").map(|m| o2_delivery(m.as_str())),
+                ..Default::default()
+            },
+        ));
+    }
+    for caps in WEIGHT_RE.captures_iter(content) {
+        found.push((
+            caps.get(0).unwrap().start(),
+            Vitals {
+                weight_kg: weight_kg(&caps),
+                ..Default::default()
+            },
+        ));
+    }
+    for caps in HEIGHT_RE.captures_iter(content) {
+        found.push((
+            caps.get(0).unwrap().start(),
+            Vitals {
+                height_cm: height_cm(&caps),
+                ..Default::default()
+            },
+        ));
+    }
+    found.sort_by_key(|(start, _)| *start);
+    found.into_iter().map(|(_, reading)| reading).collect()
+}
+
+fn matches<'t>(
+    content: &'t str,
+    named: &Regex,
+    short: &Regex,
+    plausible: impl Fn(&Captures) -> bool,
+) -> Vec<(usize, Captures<'t>)> {
+    let mut found: Vec<(usize, Captures<'t>)> = named
+        .captures_iter(content)
+        .map(|caps| (caps.get(0).unwrap().start(), caps))
+        .collect();
+    let mut offset = 0;
+    for line in content.split('\n') {
+        for caps in short.captures_iter(line) {
+            if plausible(&caps) || CONTEXT_RE.is_match(line) {
+                found.push((offset + caps.get(0).unwrap().start(), caps));
+            }
+        }
+        offset += line.len() + 1;
+    }
+    found
+}
+
+fn temperature_c(caps: &Captures) -> Option<f64> {
+    let value: f64 = caps["value"].parse().ok()?;
+    let fahrenheit = match caps.name("unit") {
+        Some(unit) => unit.as_str().eq_ignore_ascii_case("f"),
+        None => value > 50.0,
+    };
+    let celsius = if fahrenheit {
+        (value - 32.0) * 5.0 / 9.0
+    } else {
+        value
+    };
+    Some(round1(celsius))
+}
+
+fn weight_kg(caps: &Captures) -> Option<f64> {
+    let value: f64 = caps["value"].parse().ok()?;
+    let pounds = caps
+        .name("unit")
+        .is_some_and(|u| !u.as_str().eq_ignore_ascii_case("kg"));
+    Some(round1(if pounds { value * LB_TO_KG } else { value }))
+}
+
+fn height_cm(caps: &Captures) -> Option<f64> {
+    if let (Some(feet), Some(inches)) = (caps.name("feet"), caps.name("inches")) {
+        let feet: f64 = feet.as_str().parse().ok()?;
+        let inches: f64 = inches.as_str().parse().ok()?;
+        return Some(round1((feet * 12.0 + inches) * IN_TO_CM));
+    }
+    let value: f64 = caps.name("value")?.as_str().parse().ok()?;
+    let unit = caps.name("unit").map(|u| u.as_str().to_lowercase());
+    let cm = match unit.as_deref() {
+        Some("m") => value * 100.0,
+        Some("in") | Some("inches") => value * IN_TO_CM,
+        Some(_) => value,
+        None if value < 3.0 => value * 100.0,
+        None => value,
+    };
+    Some(round1(cm))
+}
+
+pub fn implausible(vitals: &Vitals) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut check = |label: &str, value: Option<f64>, unit: &str, min: f64, max: f64| {
+        if let Some(value) = value.filter(|v| *v < min || *v > max) {
+            found.push(format!(
+                "{} {}{} is outside {}-{}{}",
+                label, value, unit, min, max, unit
+            ));
+        }
+    };
+    check(
+        "Systolic BP",
+        vitals.bp_systolic.map(f64::from),
+        " mmHg",
+        50.0,
+        300.0,
+    );
+    check(
+        "Diastolic BP",
+        vitals.bp_diastolic.map(f64::from),
+        " mmHg",
+        20.0,
+        200.0,
+    );
+    check(
+        "Heart rate",
+        vitals.heart_rate.map(f64::from),
+        " /min",
+        20.0,
+        250.0,
+    );
+    check(
+        "Respiratory rate",
+        vitals.respiratory_rate.map(f64::from),
+        " /min",
+        4.0,
+        70.0,
+    );
+    check("Temperature", vitals.temperature_c, " C", 25.0, 45.0);
+    check("SpO2", vitals.spo2.map(f64::from), "%", 50.0, 100.0);
+    check("Weight", vitals.weight_kg, " kg", 0.3, 400.0);
+    check("Height", vitals.height_cm, " cm", 25.0, 250.0);
+    if let (Some(sys), Some(dia)) = (vitals.bp_systolic, vitals.bp_diastolic) {
+        if dia >= sys {
+            found.push(format!(
+                "Diastolic BP {} mmHg is not below systolic {} mmHg",
+                dia, sys
+            ));
+        }
+    }
+    found
+}
+
+fn o2_delivery(raw: &str) -> String {
+    let lower = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    match lower.to_lowercase().as_str() {
+        "ra" | "room air" => "room air".to_string(),
+        "nc" | "nasal cannula" => "nasal cannula".to_string(),
+        "nrb" | "non-rebreather" => "non-rebreather".to_string(),
+        "bipap" => "BiPAP".to_string(),
+        "cpap" => "CPAP".to_string(),
+        "hfnc" | "high flow" => "high flow".to_string(),
+        _ => lower,
+    }
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
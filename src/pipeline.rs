@@ -0,0 +1,192 @@
+use crate::config::Config;
+use crate::models::{
+    BundleMode, FormatSelection, NoteChunk, NoteFormat, ParseWarning, SectionCandidate,
+    StructuredNote,
+};
+use crate::parser::{self, Extractors, ParseOptions};
+use crate::util;
+use crate::validate::{self, RuleSet, Severity, ValidationIssue, ValidationReport};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+pub trait PipelineHooks {
+    fn after_split(&self, _notes: &mut Vec<NoteChunk>, _warnings: &mut Vec<ParseWarning>) {}
+
+    fn review_candidates(
+        &self,
+        _note_index: usize,
+        candidates: Vec<SectionCandidate>,
+    ) -> Result<Vec<SectionCandidate>> {
+        Ok(candidates)
+    }
+
+    fn after_build(&self, _note: &mut StructuredNote) {}
+}
+
+pub struct NoHooks;
+
+impl PipelineHooks for NoHooks {}
+
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    pub format: FormatSelection,
+    pub bundle_mode: BundleMode,
+    pub apply_heuristics: bool,
+    pub strict: bool,
+    pub reproducible: bool,
+    pub fuzzy_headings: bool,
+    pub note_offset: usize,
+}
+
+impl PipelineOptions {
+    pub fn from_config(config: &Config, format: impl Into<FormatSelection>) -> Self {
+        Self {
+            format: format.into(),
+            bundle_mode: config.bundle.mode_default,
+            apply_heuristics: config.enable_fallback_heuristics,
+            strict: false,
+            reproducible: util::source_date_epoch().is_some(),
+            fuzzy_headings: config.fuzzy_headings.enabled,
+            note_offset: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    pub notes: Vec<StructuredNote>,
+    pub reports: Vec<ValidationReport>,
+}
+
+impl PipelineOutput {
+    pub fn issues(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.reports.iter().flat_map(|r| r.issues.iter())
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues().any(|i| i.severity == Severity::Error)
+    }
+}
+
+pub struct Pipeline {
+    config: Config,
+    options: PipelineOptions,
+    extractors: Extractors,
+    rules: HashMap<NoteFormat, RuleSet>,
+    hooks: Box<dyn PipelineHooks>,
+}
+
+impl Pipeline {
+    pub fn new(config: Config, options: PipelineOptions) -> Self {
+        Self {
+            extractors: Extractors::from_config(&config),
+            rules: config
+                .formats
+                .names()
+                .into_iter()
+                .filter_map(|format| {
+                    let rules = RuleSet::resolve(&config, &format).ok()?;
+                    Some((format, rules))
+                })
+                .collect(),
+            config,
+            options,
+            hooks: Box::new(NoHooks),
+        }
+    }
+
+    pub fn with_hooks(mut self, hooks: impl PipelineHooks + 'static) -> Self {
+        self.hooks = Box::new(hooks);
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn options(&self) -> &PipelineOptions {
+        &self.options
+    }
+
+    pub fn parse_file(&self, path: &Path) -> Result<PipelineOutput> {
+        let text = util::read_to_string(path)?;
+        self.parse_str(&text, Some(path.display().to_string()))
+    }
+
+    pub fn parse_reader<R: Read>(
+        &self,
+        mut reader: R,
+        source_file: Option<String>,
+    ) -> Result<PipelineOutput> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        self.parse_str(&text, source_file)
+    }
+
+    pub fn parse_str(&self, text: &str, source_file: Option<String>) -> Result<PipelineOutput> {
+        let (mut chunks, mut bundle_warnings) =
+            parser::split_bundle(text, self.options.bundle_mode, &self.config);
+        self.hooks.after_split(&mut chunks, &mut bundle_warnings);
+
+        let parse_options = ParseOptions {
+            apply_heuristics: self.options.apply_heuristics,
+            fuzzy_headings: self.options.fuzzy_headings
+                && self.config.fuzzy_headings.active(self.options.strict),
+            reproducible: self.options.reproducible,
+        };
+        let mut notes = Vec::new();
+        let mut reports = Vec::new();
+        for (idx, chunk) in chunks.iter().enumerate() {
+            let note_index = self.options.note_offset + idx + 1;
+            let (format, detection, mut warnings) =
+                parser::chunk_format(chunk, &self.options.format, &self.config);
+            let (candidates, extract_warnings) = parser::extract_chunk_candidates(
+                chunk,
+                format.clone(),
+                &self.config,
+                parse_options,
+            );
+            warnings.extend(extract_warnings);
+            warnings.extend(bundle_warnings.clone());
+            let candidates = self.hooks.review_candidates(note_index, candidates)?;
+
+            let mut note = parser::build_chunk_note(
+                chunk,
+                candidates,
+                format.clone(),
+                source_file.clone(),
+                note_index,
+                warnings,
+            );
+            note.metadata.format_detection = detection;
+            parser::attach_header(&mut note, &chunk.text, &self.config);
+            parser::attach_extractions(&mut note, chunk, &self.config, &self.extractors);
+            if parse_options.reproducible {
+                note.metadata.generated_at = util::timestamp(true);
+            }
+            self.hooks.after_build(&mut note);
+
+            let resolved;
+            let rules = match self.rules.get(&format) {
+                Some(rules) => rules,
+                None => {
+                    resolved = RuleSet::resolve(&self.config, &format)?;
+                    &resolved
+                }
+            };
+            let issues = validate::validate_chunk_with_rules(
+                &note,
+                chunk,
+                rules,
+                self.options.strict,
+                &self.config,
+            );
+            reports.push(ValidationReport { note_index, issues });
+            notes.push(note);
+        }
+
+        Ok(PipelineOutput { notes, reports })
+    }
+}
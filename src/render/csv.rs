@@ -1,7 +1,73 @@
-use crate::models::{CsvLayout, NoteFormat, StructuredNote};
+use crate::models::{CsvLayout, StructuredNote};
 use anyhow::Result;
 use csv::Writer;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+
+const ENCOUNTER_COLUMNS: [&str; 6] = [
+    "encounter_position",
+    "encounter_total",
+    "encounter_line_start",
+    "encounter_line_end",
+    "date_of_service",
+    "encounter_header",
+];
+
+fn encounter_values(note: &StructuredNote) -> Vec<String> {
+    match &note.encounter {
+        Some(encounter) => vec![
+            encounter.position.to_string(),
+            encounter.bundle_size.to_string(),
+            encounter.line_start.to_string(),
+            encounter.line_end.to_string(),
+            encounter.date_of_service.clone().unwrap_or_default(),
+            encounter.header_line.clone().unwrap_or_default(),
+        ],
+        None => vec![String::new(); ENCOUNTER_COLUMNS.len()],
+    }
+}
+
+const VITAL_COLUMNS: [&str; 9] = [
+    "vitals_bp_systolic",
+    "vitals_bp_diastolic",
+    "vitals_heart_rate",
+    "vitals_respiratory_rate",
+    "vitals_temperature_c",
+    "vitals_spo2",
+    "vitals_o2_delivery",
+    "vitals_weight_kg",
+    "vitals_height_cm",
+];
+
+fn vital_values(note: &StructuredNote) -> Vec<String> {
+    fn text<T: ToString>(value: Option<T>) -> String {
+        value.map(|v| v.to_string()).unwrap_or_default()
+    }
+    match &note.vitals {
+        Some(vitals) => vec![
+            text(vitals.bp_systolic),
+            text(vitals.bp_diastolic),
+            text(vitals.heart_rate),
+            text(vitals.respiratory_rate),
+            text(vitals.temperature_c),
+            text(vitals.spo2),
+            text(vitals.o2_delivery.as_ref()),
+            text(vitals.weight_kg),
+            text(vitals.height_cm),
+        ],
+        None => vec![String::new(); VITAL_COLUMNS.len()],
+    }
+}
+
+fn header_keys(notes: &[StructuredNote]) -> Vec<String> {
+    let keys: BTreeSet<&String> = notes.iter().flat_map(|n| n.header.keys()).collect();
+    keys.into_iter().cloned().collect()
+}
+
+fn header_values(note: &StructuredNote, keys: &[String]) -> Vec<String> {
+    keys.iter()
+        .map(|key| note.header.get(key).cloned().unwrap_or_default())
+        .collect()
+}
 
 pub fn render_notes(notes: &[StructuredNote], layout: CsvLayout) -> Result<String> {
     match layout {
@@ -21,20 +87,38 @@ fn render_wide(notes: &[StructuredNote]) -> Result<String> {
         }
     }
 
+    let header_fields = header_keys(notes);
     let mut wtr = Writer::from_writer(vec![]);
     let mut header = vec!["id", "format", "source_file", "note_index"]
         .into_iter()
         .map(|s| s.to_string())
         .collect::<Vec<_>>();
+    let with_encounter = notes.iter().any(|n| n.encounter.is_some());
+    if with_encounter {
+        header.extend(ENCOUNTER_COLUMNS.iter().map(|s| s.to_string()));
+    }
+    header.extend(header_fields.iter().map(|k| format!("header_{}", k)));
+    let with_vitals = notes.iter().any(|n| n.vitals.is_some());
+    if with_vitals {
+        header.extend(VITAL_COLUMNS.iter().map(|s| s.to_string()));
+    }
     header.extend(section_names.iter().cloned());
     wtr.write_record(&header)?;
 
     for note in notes {
-        let mut record = Vec::new();
-        record.push(note.id.clone());
-        record.push(format_label(note.format).to_string());
-        record.push(note.source_file.clone().unwrap_or_default());
-        record.push(note.note_index.to_string());
+        let mut record = vec![
+            note.id.clone(),
+            note.format.to_string(),
+            note.source_file.clone().unwrap_or_default(),
+            note.note_index.to_string(),
+        ];
+        if with_encounter {
+            record.extend(encounter_values(note));
+        }
+        record.extend(header_values(note, &header_fields));
+        if with_vitals {
+            record.extend(vital_values(note));
+        }
 
         for name in &section_names {
             let value = note
@@ -52,38 +136,157 @@ fn render_wide(notes: &[StructuredNote]) -> Result<String> {
     Ok(String::from_utf8(data)?)
 }
 
-fn render_long(notes: &[StructuredNote]) -> Result<String> {
+pub fn render_medications(notes: &[StructuredNote]) -> Result<String> {
     let mut wtr = Writer::from_writer(vec![]);
     wtr.write_record([
         "note_id",
-        "format",
         "source_file",
         "note_index",
-        "section_name",
-        "content",
+        "section",
+        "name",
+        "strength",
+        "unit",
+        "route",
+        "frequency",
+        "prn",
+        "status",
+        "line_start",
+        "line_end",
+        "raw",
     ])?;
-
     for note in notes {
-        for section in &note.sections {
+        for medication in &note.medications {
             wtr.write_record([
-                note.id.as_str(),
-                format_label(note.format),
-                note.source_file.as_deref().unwrap_or(""),
-                &note.note_index.to_string(),
-                section.name.as_str(),
-                section.content.as_str(),
+                note.id.clone(),
+                note.source_file.clone().unwrap_or_default(),
+                note.note_index.to_string(),
+                medication.section.clone(),
+                medication.name.clone(),
+                medication.strength.clone().unwrap_or_default(),
+                medication.unit.clone().unwrap_or_default(),
+                medication.route.clone().unwrap_or_default(),
+                medication.frequency.clone().unwrap_or_default(),
+                medication.prn.to_string(),
+                medication
+                    .status
+                    .map(|s| s.as_str().to_string())
+                    .unwrap_or_default(),
+                span_line(medication.span.map(|s| s.line_start)),
+                span_line(medication.span.map(|s| s.line_end)),
+                medication.raw.clone(),
             ])?;
         }
     }
+    let data = wtr.into_inner()?;
+    Ok(String::from_utf8(data)?)
+}
 
+pub fn render_labs(notes: &[StructuredNote]) -> Result<String> {
+    let mut wtr = Writer::from_writer(vec![]);
+    wtr.write_record([
+        "note_id",
+        "source_file",
+        "note_index",
+        "section",
+        "analyte",
+        "name",
+        "comparator",
+        "value",
+        "unit",
+        "reference_low",
+        "reference_high",
+        "flag",
+        "line_start",
+        "line_end",
+        "raw",
+    ])?;
+    for note in notes {
+        for lab in &note.labs {
+            wtr.write_record([
+                note.id.clone(),
+                note.source_file.clone().unwrap_or_default(),
+                note.note_index.to_string(),
+                lab.section.clone(),
+                lab.analyte.clone(),
+                lab.name.clone(),
+                lab.comparator.clone().unwrap_or_default(),
+                lab.value.to_string(),
+                lab.unit.clone().unwrap_or_default(),
+                lab.reference_low.map(|v| v.to_string()).unwrap_or_default(),
+                lab.reference_high
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+                lab.flag.map(|f| f.as_str().to_string()).unwrap_or_default(),
+                span_line(lab.span.map(|s| s.line_start)),
+                span_line(lab.span.map(|s| s.line_end)),
+                lab.raw.clone(),
+            ])?;
+        }
+    }
     let data = wtr.into_inner()?;
     Ok(String::from_utf8(data)?)
 }
 
-fn format_label(format: NoteFormat) -> &'static str {
-    match format {
-        NoteFormat::Soap => "soap",
-        NoteFormat::Hp => "hp",
-        NoteFormat::Discharge => "discharge",
+fn span_line(line: Option<usize>) -> String {
+    line.map(|l| l.to_string()).unwrap_or_default()
+}
+
+fn render_long(notes: &[StructuredNote]) -> Result<String> {
+    let header_fields = header_keys(notes);
+    let mut wtr = Writer::from_writer(vec![]);
+    let mut header = vec!["note_id", "format", "source_file", "note_index"]
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    let with_encounter = notes.iter().any(|n| n.encounter.is_some());
+    if with_encounter {
+        header.extend(ENCOUNTER_COLUMNS.iter().map(|s| s.to_string()));
+    }
+    header.extend(header_fields.iter().map(|k| format!("header_{}", k)));
+    header.extend(["section_name".to_string(), "content".to_string()]);
+    wtr.write_record(&header)?;
+
+    for note in notes {
+        let mut note_columns = Vec::new();
+        if with_encounter {
+            note_columns.extend(encounter_values(note));
+        }
+        note_columns.extend(header_values(note, &header_fields));
+        for section in &note.sections {
+            let mut record = vec![
+                note.id.clone(),
+                note.format.to_string(),
+                note.source_file.clone().unwrap_or_default(),
+                note.note_index.to_string(),
+            ];
+            record.extend(note_columns.iter().cloned());
+            record.push(section.name.clone());
+            record.push(section.content.clone());
+            wtr.write_record(&record)?;
+        }
+        for (idx, problem) in note.problems.iter().enumerate() {
+            let label = format!("Problem {}", problem.number.unwrap_or(idx + 1));
+            let rows = std::iter::once((label.clone(), problem.title.clone())).chain(
+                problem
+                    .plan
+                    .iter()
+                    .map(|item| (format!("{} Plan", label), item.clone())),
+            );
+            for (name, content) in rows {
+                let mut record = vec![
+                    note.id.clone(),
+                    note.format.to_string(),
+                    note.source_file.clone().unwrap_or_default(),
+                    note.note_index.to_string(),
+                ];
+                record.extend(note_columns.iter().cloned());
+                record.push(name);
+                record.push(content);
+                wtr.write_record(&record)?;
+            }
+        }
     }
+
+    let data = wtr.into_inner()?;
+    Ok(String::from_utf8(data)?)
 }
@@ -4,13 +4,23 @@ pub fn render_notes(notes: &[StructuredNote]) -> String {
     let mut out = Vec::new();
     for (idx, note) in notes.iter().enumerate() {
         out.push(format!("# Structured Note {}", idx + 1));
-        out.push(format!("Format: {:?}", note.format));
+        out.push(format!("Format: {}", note.format.label()));
         if let Some(source) = &note.source_file {
             out.push(format!("Source: {}", source));
         }
+        if !note.header.is_empty() {
+            out.push(String::new());
+            out.push("Header:".to_string());
+            for (key, value) in &note.header {
+                out.push(format!("- {}: {}", key, value));
+            }
+        }
         out.push(String::new());
         for section in &note.sections {
-            out.push(format!("## {}", section.name));
+            out.push(format!(
+                "## {}",
+                section.label.as_deref().unwrap_or(&section.name)
+            ));
             if section.content.is_empty() {
                 out.push("(empty)".to_string());
             } else {
@@ -18,6 +28,20 @@ pub fn render_notes(notes: &[StructuredNote]) -> String {
             }
             out.push(String::new());
         }
+        if !note.problems.is_empty() {
+            out.push("## Problems".to_string());
+            for (number, problem) in note.problems.iter().enumerate() {
+                out.push(format!(
+                    "{}. {}",
+                    problem.number.unwrap_or(number + 1),
+                    problem.title
+                ));
+                for item in &problem.plan {
+                    out.push(format!("   - {}", item));
+                }
+            }
+            out.push(String::new());
+        }
         if idx + 1 < notes.len() {
             out.push("---".to_string());
             out.push(String::new());
@@ -26,6 +26,17 @@ impl OutputFormat {
     }
 }
 
+pub fn render_tables(notes: &[StructuredNote]) -> Result<Vec<(&'static str, String)>> {
+    let mut tables = Vec::new();
+    if notes.iter().any(|n| !n.medications.is_empty()) {
+        tables.push(("medications", csv::render_medications(notes)?));
+    }
+    if notes.iter().any(|n| !n.labs.is_empty()) {
+        tables.push(("labs", csv::render_labs(notes)?));
+    }
+    Ok(tables)
+}
+
 pub fn render_notes(
     notes: &[StructuredNote],
     format: OutputFormat,
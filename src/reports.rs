@@ -1,7 +1,7 @@
 use crate::models::StructuredNote;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -10,6 +10,13 @@ pub struct BatchFailure {
     pub error: String,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FormatCounts {
+    pub notes: usize,
+    pub ambiguous: usize,
+    pub sections: BTreeMap<String, usize>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BatchReport {
     pub tool_name: String,
@@ -17,7 +24,9 @@ pub struct BatchReport {
     pub total_files: usize,
     pub ok_files: usize,
     pub failed_files: usize,
-    pub counts_by_section: HashMap<String, usize>,
+    pub counts_by_section: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub counts_by_format: BTreeMap<String, FormatCounts>,
     pub warnings_count: usize,
     pub failures: Vec<BatchFailure>,
     pub runtime_ms: u128,
@@ -31,7 +40,8 @@ impl BatchReport {
             total_files: 0,
             ok_files: 0,
             failed_files: 0,
-            counts_by_section: HashMap::new(),
+            counts_by_section: BTreeMap::new(),
+            counts_by_format: BTreeMap::new(),
             warnings_count: 0,
             failures: Vec::new(),
             runtime_ms: 0,
@@ -41,11 +51,25 @@ impl BatchReport {
     pub fn record_ok(&mut self, notes: &[StructuredNote]) {
         self.ok_files += 1;
         for note in notes {
+            let by_format = self
+                .counts_by_format
+                .entry(note.format.to_string())
+                .or_default();
+            by_format.notes += 1;
+            if note
+                .metadata
+                .format_detection
+                .as_ref()
+                .is_some_and(|d| d.ambiguous)
+            {
+                by_format.ambiguous += 1;
+            }
             for section in &note.sections {
                 *self
                     .counts_by_section
                     .entry(section.name.clone())
                     .or_insert(0) += 1;
+                *by_format.sections.entry(section.name.clone()).or_insert(0) += 1;
             }
             self.warnings_count += note.warnings.len();
         }
@@ -63,6 +87,27 @@ impl BatchReport {
         self.total_files = self.ok_files + self.failed_files;
     }
 
+    pub fn to_csv(&self) -> Result<String> {
+        let mut wtr = csv::Writer::from_writer(vec![]);
+        wtr.write_record(["format", "notes", "ambiguous", "section", "count"])?;
+        for (format, counts) in &self.counts_by_format {
+            let notes = counts.notes.to_string();
+            let ambiguous = counts.ambiguous.to_string();
+            wtr.write_record([format.as_str(), &notes, &ambiguous, "", ""])?;
+            for (section, count) in &counts.sections {
+                wtr.write_record([
+                    format.as_str(),
+                    &notes,
+                    &ambiguous,
+                    section,
+                    &count.to_string(),
+                ])?;
+            }
+        }
+        let data = wtr.into_inner()?;
+        Ok(String::from_utf8(data)?)
+    }
+
     pub fn write_to(&self, path: &Path) -> Result<()> {
         let json = serde_json::to_string_pretty(self)?;
         crate::util::write_string(path, &json)?;
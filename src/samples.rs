@@ -85,6 +85,7 @@ fn synthetic_note(format: NoteFormat, index: usize) -> (String, StructuredNote)
                 vec!["Instructions:", "Discharge Instructions:"],
             ),
         ],
+        NoteFormat::Custom(_) => Vec::new(),
     };
 
     for (idx, (name, variants)) in section_defs.iter().enumerate() {
@@ -100,22 +101,38 @@ fn synthetic_note(format: NoteFormat, index: usize) -> (String, StructuredNote)
         }
         sections.push(Section {
             name: name.to_string(),
+            label: None,
+            code: None,
             content,
             confidence: 0.95,
+            span: None,
+            heading_span: None,
+            heading_style: None,
         });
     }
 
     let note = StructuredNote {
         id: format!("sample-{}", index),
-        format,
+        format: format.clone(),
         source_file: None,
         note_index: index,
         sections,
         warnings: Vec::new(),
         metadata: Metadata {
-            generated_at: util::now_iso(),
+            generated_at: util::timestamp(false),
             tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            ..Default::default()
         },
+        header: [
+            (
+                "patient_name".to_string(),
+                format!("Synthetic Demo {}", index),
+            ),
+            ("dob".to_string(), "1990-01-01".to_string()),
+        ]
+        .into_iter()
+        .collect(),
+        ..Default::default()
     };
 
     (text.trim().to_string(), note)
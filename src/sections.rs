@@ -0,0 +1,193 @@
+use crate::config::{Config, SectionDef};
+use crate::models::{SectionCandidate, SectionName};
+use crate::util;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+const BUILTIN_SECTIONS: &[(SectionName, &[&str])] = &[
+    (SectionName::Subjective, &["SUBJECTIVE", "S"]),
+    (SectionName::Objective, &["OBJECTIVE", "O"]),
+    (
+        SectionName::Assessment,
+        &["ASSESSMENT", "DIAGNOSIS", "DX", "A"],
+    ),
+    (SectionName::Plan, &["PLAN", "P"]),
+    (
+        SectionName::AssessmentPlan,
+        &[
+            "ASSESSMENT AND PLAN",
+            "ASSESSMENT PLAN",
+            "A P",
+            "IMPRESSION AND PLAN",
+            "IMPRESSION PLAN",
+        ],
+    ),
+    (SectionName::ChiefComplaint, &["CHIEF COMPLAINT", "CC"]),
+    (SectionName::Hpi, &["HPI", "HISTORY OF PRESENT ILLNESS"]),
+    (SectionName::Pmh, &["PMH", "PAST MEDICAL HISTORY", "HX"]),
+    (SectionName::Medications, &["MEDICATIONS", "MEDS"]),
+    (SectionName::Allergies, &["ALLERGIES", "ALLERGY"]),
+    (SectionName::Ros, &["ROS", "REVIEW OF SYSTEMS"]),
+    (
+        SectionName::PhysicalExam,
+        &["PHYSICAL EXAM", "PHYSICAL EXAMINATION", "PE"],
+    ),
+    (
+        SectionName::AdmissionDx,
+        &["ADMISSION DX", "ADMISSION DIAGNOSIS", "ADMIT DX"],
+    ),
+    (
+        SectionName::DischargeDx,
+        &["DISCHARGE DX", "DISCHARGE DIAGNOSIS"],
+    ),
+    (SectionName::HospitalCourse, &["HOSPITAL COURSE", "COURSE"]),
+    (SectionName::FollowUp, &["FOLLOW UP", "FOLLOWUP"]),
+    (SectionName::Disposition, &["DISPOSITION", "DISPO"]),
+    (
+        SectionName::Instructions,
+        &["INSTRUCTIONS", "DISCHARGE INSTRUCTIONS"],
+    ),
+];
+
+static BUILTIN_HEADINGS: Lazy<HashMap<String, SectionName>> = Lazy::new(|| {
+    let mut map = HashMap::new();
+    for (name, aliases) in BUILTIN_SECTIONS {
+        for alias in aliases.iter() {
+            map.insert(util::normalize_heading_key(alias), name.clone());
+        }
+    }
+    map
+});
+
+pub fn builtin_heading(raw: &str) -> Option<SectionName> {
+    BUILTIN_HEADINGS
+        .get(&util::normalize_heading_key(raw))
+        .cloned()
+}
+
+pub fn builtin_section(raw: &str) -> Option<SectionName> {
+    let key = util::normalize_heading_key(raw);
+    if key == "NARRATIVE" || key == "OTHER" {
+        return Some(SectionName::Narrative);
+    }
+    if let Some(name) = BUILTIN_SECTIONS
+        .iter()
+        .map(|(name, _)| name)
+        .find(|name| util::normalize_heading_key(name.as_str()) == key)
+    {
+        return Some(name.clone());
+    }
+    builtin_heading(raw)
+}
+
+pub fn combined_parts(name: &str) -> Option<[SectionName; 2]> {
+    let key = util::normalize_heading_key(name);
+    if key == util::normalize_heading_key(SectionName::AssessmentPlan.as_str()) {
+        Some([SectionName::Assessment, SectionName::Plan])
+    } else {
+        None
+    }
+}
+
+pub fn custom_heading(config: &Config, raw: &str) -> Option<String> {
+    let key = util::normalize_heading_key(raw);
+    config.sections.iter().find_map(|(name, def)| {
+        let matches = util::normalize_heading_key(name) == key
+            || def
+                .aliases
+                .iter()
+                .any(|alias| util::normalize_heading_key(alias) == key);
+        if matches {
+            Some(name.clone())
+        } else {
+            None
+        }
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct SectionEntry {
+    pub name: String,
+    pub label: String,
+    pub code: Option<String>,
+    pub aliases: Vec<String>,
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SectionRegistry {
+    entries: Vec<SectionEntry>,
+}
+
+impl SectionRegistry {
+    pub fn builtin() -> Self {
+        let entries = BUILTIN_SECTIONS
+            .iter()
+            .map(|(name, aliases)| SectionEntry {
+                name: name.as_str().to_string(),
+                label: name.as_str().to_string(),
+                code: None,
+                aliases: aliases.iter().map(|a| a.to_string()).collect(),
+                builtin: true,
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let mut registry = Self::builtin();
+        for (name, def) in &config.sections {
+            registry.register(name, def);
+        }
+        registry
+    }
+
+    pub fn register(&mut self, name: &str, def: &SectionDef) {
+        let entry = SectionEntry {
+            name: name.to_string(),
+            label: def.label.clone().unwrap_or_else(|| name.to_string()),
+            code: def.code.clone(),
+            aliases: def.aliases.clone(),
+            builtin: false,
+        };
+        let key = util::normalize_heading_key(name);
+        match self
+            .entries
+            .iter_mut()
+            .find(|e| util::normalize_heading_key(&e.name) == key)
+        {
+            Some(existing) => {
+                existing.label = entry.label;
+                existing.code = entry.code;
+                existing.aliases.extend(entry.aliases);
+            }
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub fn entries(&self) -> &[SectionEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SectionEntry> {
+        let key = util::normalize_heading_key(name);
+        self.entries
+            .iter()
+            .find(|e| util::normalize_heading_key(&e.name) == key)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn annotate(&self, candidates: &mut [SectionCandidate]) {
+        for candidate in candidates.iter_mut() {
+            if let Some(entry) = self.get(&candidate.name) {
+                if entry.label != candidate.name {
+                    candidate.label = Some(entry.label.clone());
+                }
+                candidate.code = entry.code.clone();
+            }
+        }
+    }
+}
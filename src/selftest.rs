@@ -1,8 +1,8 @@
 use crate::config::Config;
-use crate::parser::{self, ParseOptions};
+use crate::pipeline::{Pipeline, PipelineOptions};
 use crate::render::{self, OutputFormat};
 use crate::util;
-use crate::validate::{self, Severity, Template, ValidationIssue};
+use crate::validate::{Severity, Template, ValidationIssue};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -36,12 +36,34 @@ pub fn run_selftest(
     strict: bool,
     out_dir: Option<&Path>,
 ) -> Result<SelftestSummary> {
-    let config = Config::default();
+    run_selftest_with_config(
+        fixtures,
+        template,
+        strict,
+        false,
+        out_dir,
+        Config::default(),
+    )
+}
+
+pub fn run_selftest_with_config(
+    fixtures: &str,
+    template: Template,
+    strict: bool,
+    reproducible: bool,
+    out_dir: Option<&Path>,
+    config: Config,
+) -> Result<SelftestSummary> {
+    config.ensure_format(&template)?;
+    let mut options = PipelineOptions::from_config(&config, template.clone());
+    options.strict = strict;
+    options.reproducible |= reproducible;
+    let pipeline = Pipeline::new(config, options);
     let files = collect_files(fixtures)?;
     let mut results = Vec::new();
 
     for path in files {
-        let result = process_file(&path, template, strict, out_dir, &config);
+        let result = process_file(&path, out_dir, &pipeline);
         results.push(result);
     }
 
@@ -60,11 +82,8 @@ fn collect_files(fixtures: &str) -> Result<Vec<PathBuf>> {
 
     if has_glob_meta(fixtures) {
         let mut files = Vec::new();
-        for entry in glob::glob(fixtures)? {
-            match entry {
-                Ok(path) => files.push(path),
-                Err(_) => {}
-            }
+        for path in glob::glob(fixtures)?.flatten() {
+            files.push(path);
         }
         files.sort();
         return Ok(files);
@@ -94,49 +113,21 @@ fn visit_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-fn process_file(
-    path: &Path,
-    template: Template,
-    strict: bool,
-    out_dir: Option<&Path>,
-    config: &Config,
-) -> FileResult {
-    match util::read_to_string(path) {
-        Ok(content) => {
-            let (note_texts, bundle_warnings) =
-                parser::split_bundle(&content, config.bundle.mode_default, config);
-            let mut all_issues = Vec::new();
-            let mut notes = Vec::new();
-
-            for (idx, note_text) in note_texts.iter().enumerate() {
-                let (candidates, mut warnings) = parser::extract_candidates(
-                    note_text,
-                    template_to_format(template),
-                    config,
-                    ParseOptions {
-                        apply_heuristics: config.enable_fallback_heuristics,
-                    },
-                );
-                warnings.extend(bundle_warnings.clone());
-                let note = parser::build_note(
-                    candidates,
-                    template_to_format(template),
-                    Some(path.display().to_string()),
-                    idx + 1,
-                    warnings,
-                );
-                let issues = validate::validate_note(&note, template, strict);
-                all_issues.extend(issues);
-                notes.push(note);
-            }
+fn process_file(path: &Path, out_dir: Option<&Path>, pipeline: &Pipeline) -> FileResult {
+    match pipeline.parse_file(path) {
+        Ok(output) => {
+            let notes = output.notes;
+            let all_issues: Vec<ValidationIssue> =
+                output.reports.into_iter().flat_map(|r| r.issues).collect();
+            let layout = pipeline.config().csv.layout;
 
             if let Some(out_dir) = out_dir {
                 let stem = util::file_stem(path);
-                let md = render::render_notes(&notes, OutputFormat::Md, config.csv.layout)
+                let md = render::render_notes(&notes, OutputFormat::Md, layout)
                     .unwrap_or_else(|_| "".to_string());
-                let json = render::render_notes(&notes, OutputFormat::Json, config.csv.layout)
+                let json = render::render_notes(&notes, OutputFormat::Json, layout)
                     .unwrap_or_else(|_| "".to_string());
-                let csv = render::render_notes(&notes, OutputFormat::Csv, config.csv.layout)
+                let csv = render::render_notes(&notes, OutputFormat::Csv, layout)
                     .unwrap_or_else(|_| "".to_string());
                 let _ = util::write_string(&out_dir.join(format!("{}.md", stem)), &md);
                 let _ = util::write_string(&out_dir.join(format!("{}.json", stem)), &json);
@@ -211,18 +202,10 @@ fn summarize(
     }
 }
 
-fn template_to_format(template: Template) -> crate::models::NoteFormat {
-    match template {
-        Template::Soap => crate::models::NoteFormat::Soap,
-        Template::Hp => crate::models::NoteFormat::Hp,
-        Template::Discharge => crate::models::NoteFormat::Discharge,
-    }
-}
-
 pub fn summarize_text(summary: &SelftestSummary) -> String {
     let mut out = String::new();
     out.push_str(&format!("Fixtures: {}\n", summary.fixtures));
-    out.push_str(&format!("Template: {:?}\n", summary.template));
+    out.push_str(&format!("Template: {}\n", summary.template.label()));
     out.push_str(&format!("Strict: {}\n", summary.strict));
     out.push_str(&format!("Total files: {}\n", summary.total_files));
     out.push_str(&format!("Total notes: {}\n", summary.total_notes));
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use chrono::DateTime;
 #[cfg(not(target_arch = "wasm32"))]
 use chrono::Utc;
 use std::fs;
@@ -15,16 +16,37 @@ pub fn normalize_heading_key(input: &str) -> String {
         if ch.is_ascii_alphanumeric() {
             out.push(ch.to_ascii_uppercase());
             last_space = false;
-        } else if ch.is_whitespace() {
-            if !last_space {
-                out.push(' ');
-                last_space = true;
-            }
+        } else if ch.is_whitespace() && !last_space {
+            out.push(' ');
+            last_space = true;
         }
     }
     out.trim().to_string()
 }
 
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
 #[cfg(target_arch = "wasm32")]
 pub fn now_iso() -> String {
     js_sys::Date::new_0().to_iso_string().into()
@@ -35,6 +57,41 @@ pub fn now_iso() -> String {
     Utc::now().to_rfc3339()
 }
 
+pub fn source_date_epoch() -> Option<i64> {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+}
+
+pub fn timestamp(reproducible: bool) -> String {
+    let pinned = match source_date_epoch() {
+        Some(secs) => Some(secs),
+        None if reproducible => Some(0),
+        None => None,
+    };
+    match pinned.and_then(|secs| DateTime::from_timestamp(secs, 0)) {
+        Some(time) => time.to_rfc3339(),
+        None => now_iso(),
+    }
+}
+
+pub fn stable_hash(parts: &[&str]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET;
+    for (idx, part) in parts.iter().enumerate() {
+        if idx > 0 {
+            hash ^= 0xff;
+            hash = hash.wrapping_mul(PRIME);
+        }
+        for byte in part.bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
 pub fn read_to_string(path: &Path) -> Result<String> {
     Ok(fs::read_to_string(path)?)
 }
@@ -1,7 +1,11 @@
-use crate::models::StructuredNote;
+use crate::config::{Config, ContentCheck, ContentRule, RequiredGroup, ValidationSpec};
+use crate::models::{NoteChunk, NoteFormat, Section, SectionName, StructuredNote};
+use crate::parser::spans::SectionCursor;
+use crate::parser::vitals;
+use crate::sections;
 use crate::util;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -17,6 +21,22 @@ pub struct Span {
     pub line_end: usize,
 }
 
+impl Span {
+    fn of_section(section: &Section) -> Option<Span> {
+        section.span.map(|span| Span {
+            line_start: span.line_start,
+            line_end: span.line_end,
+        })
+    }
+
+    fn at_line(line: usize) -> Span {
+        Span {
+            line_start: line,
+            line_end: line,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationIssue {
     pub code: String,
@@ -26,13 +46,12 @@ pub struct ValidationIssue {
     pub span: Option<Span>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, clap::ValueEnum)]
-#[serde(rename_all = "lowercase")]
-#[clap(rename_all = "lowercase")]
-pub enum Template {
-    Soap,
-    Hp,
-    Discharge,
+pub type Template = NoteFormat;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub note_index: usize,
+    pub issues: Vec<ValidationIssue>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,16 +61,232 @@ pub struct SectionSummary {
     pub char_count: usize,
 }
 
-const MIN_SECTION_LEN: usize = 20;
+pub const DEFAULT_MIN_SECTION_LEN: usize = 20;
+
+pub const ISSUE_CODES: [&str; 8] = [
+    "missing_required",
+    "duplicate_section",
+    "unknown_section",
+    "allergy_status_missing",
+    "ros_incomplete",
+    "implausible_vital",
+    "section_too_short",
+    "section_too_long",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleSet {
+    pub format: NoteFormat,
+    pub required: Vec<RequiredGroup>,
+    pub optional: Vec<String>,
+    pub min_length: usize,
+    pub min_lengths: BTreeMap<String, usize>,
+    pub max_lengths: BTreeMap<String, usize>,
+    pub severity: BTreeMap<String, Severity>,
+    pub content: Vec<CompiledRule>,
+    pub ros_min_systems: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompiledRule {
+    #[serde(flatten)]
+    pub rule: ContentRule,
+    #[serde(skip)]
+    pub checks: Vec<ContentCheck>,
+}
+
+impl CompiledRule {
+    pub fn compile(rule: &ContentRule) -> Result<Self, regex::Error> {
+        Ok(Self {
+            rule: rule.clone(),
+            checks: rule.checks()?,
+        })
+    }
+}
+
+impl RuleSet {
+    pub fn resolve(config: &Config, format: &NoteFormat) -> Result<Self, regex::Error> {
+        let mut required = ValidationSpec::builtin_required(format);
+        if let Some(spec) = config.formats.get(format) {
+            if !spec.required_sections.is_empty() || matches!(format, NoteFormat::Custom(_)) {
+                required = spec
+                    .required_sections
+                    .iter()
+                    .map(|name| RequiredGroup {
+                        section: name.as_str().to_string(),
+                        aliases: Vec::new(),
+                    })
+                    .collect();
+            }
+        }
+        let mut rules = RuleSet {
+            format: format.clone(),
+            required,
+            optional: ValidationSpec::builtin_optional(format),
+            min_length: DEFAULT_MIN_SECTION_LEN,
+            min_lengths: BTreeMap::new(),
+            max_lengths: BTreeMap::new(),
+            severity: BTreeMap::new(),
+            content: Vec::new(),
+            ros_min_systems: ValidationSpec::builtin_ros_min_systems(format),
+        };
+        if let Some(spec) = config.validation.get(format.as_str()) {
+            if let Some(required) = &spec.required {
+                rules.required = required.clone();
+            }
+            if let Some(optional) = &spec.optional {
+                rules.optional = optional.clone();
+            }
+            if let Some(min_length) = spec.min_length {
+                rules.min_length = min_length;
+            }
+            rules.min_lengths.extend(spec.min_lengths.clone());
+            rules.max_lengths.extend(spec.max_lengths.clone());
+            rules.severity.extend(spec.severity.clone());
+            for rule in &spec.rules {
+                rules.content.push(CompiledRule::compile(rule)?);
+            }
+            if let Some(min) = spec.ros_min_systems {
+                rules.ros_min_systems = min;
+            }
+        }
+        Ok(rules)
+    }
+
+    pub fn min_length_for(&self, section: &str) -> usize {
+        lookup_section(&self.min_lengths, section).unwrap_or(self.min_length)
+    }
+
+    pub fn max_length_for(&self, section: &str) -> Option<usize> {
+        lookup_section(&self.max_lengths, section)
+    }
+
+    fn severity_for(&self, code: &str, default: Severity) -> Severity {
+        self.severity.get(code).copied().unwrap_or(default)
+    }
+
+    pub fn describe(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("Validation rules ({}):\n", self.format));
+        let required: Vec<String> = self
+            .required
+            .iter()
+            .map(|group| {
+                if group.aliases.is_empty() {
+                    group.section.clone()
+                } else {
+                    format!("{} ({})", group.section, group.aliases.join(", "))
+                }
+            })
+            .collect();
+        out.push_str(&format!("- required: {}\n", list_or_none(&required)));
+        out.push_str(&format!("- optional: {}\n", list_or_none(&self.optional)));
+        let mut min = vec![self.min_length.to_string()];
+        min.extend(self.min_lengths.iter().map(|(k, v)| format!("{}={}", k, v)));
+        out.push_str(&format!("- min length: {}\n", min.join(", ")));
+        let max: Vec<String> = self
+            .max_lengths
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        out.push_str(&format!("- max length: {}\n", list_or_none(&max)));
+        let severity: Vec<String> = self
+            .severity
+            .iter()
+            .map(|(k, v)| format!("{}={:?}", k, v))
+            .collect();
+        out.push_str(&format!(
+            "- severity overrides: {}\n",
+            list_or_none(&severity)
+        ));
+        let content: Vec<String> = self
+            .content
+            .iter()
+            .map(|compiled| format!("{} ({})", compiled.rule.code, compiled.rule.section))
+            .collect();
+        out.push_str(&format!("- content rules: {}\n", list_or_none(&content)));
+        if self.ros_min_systems > 0 {
+            out.push_str(&format!("- ROS min systems: {}\n", self.ros_min_systems));
+        }
+        out
+    }
+
+    fn known_sections(&self, config: &Config) -> HashSet<String> {
+        let mut all = HashSet::new();
+        for group in &self.required {
+            for name in group.names() {
+                all.insert(util::normalize_heading_key(name));
+            }
+        }
+        for name in &self.optional {
+            all.insert(util::normalize_heading_key(name));
+        }
+        for name in config.section_order(&self.format) {
+            all.insert(util::normalize_heading_key(&name));
+        }
+        let combined = SectionName::AssessmentPlan;
+        if sections::combined_parts(combined.as_str())
+            .is_some_and(|parts| parts.iter().any(|part| all.contains(&section_key(part))))
+        {
+            all.insert(section_key(&combined));
+        }
+        all
+    }
+}
+
+fn lookup_section(map: &BTreeMap<String, usize>, section: &str) -> Option<usize> {
+    let key = util::normalize_heading_key(section);
+    map.iter()
+        .find(|(name, _)| util::normalize_heading_key(name) == key)
+        .map(|(_, value)| *value)
+}
+
+fn list_or_none(items: &[String]) -> String {
+    if items.is_empty() {
+        "(none)".to_string()
+    } else {
+        items.join(", ")
+    }
+}
 
 pub fn validate_note(
     note: &StructuredNote,
-    template: Template,
+    template: &Template,
+    strict: bool,
+    config: &Config,
+) -> Result<Vec<ValidationIssue>, regex::Error> {
+    let rules = RuleSet::resolve(config, template)?;
+    Ok(validate_with_rules(note, &rules, strict, config))
+}
+
+pub fn validate_with_rules(
+    note: &StructuredNote,
+    rules: &RuleSet,
     strict: bool,
+    config: &Config,
+) -> Vec<ValidationIssue> {
+    check_note(note, None, rules, strict, config)
+}
+
+pub fn validate_chunk_with_rules(
+    note: &StructuredNote,
+    chunk: &NoteChunk,
+    rules: &RuleSet,
+    strict: bool,
+    config: &Config,
+) -> Vec<ValidationIssue> {
+    check_note(note, Some(chunk), rules, strict, config)
+}
+
+fn check_note(
+    note: &StructuredNote,
+    chunk: Option<&NoteChunk>,
+    rules: &RuleSet,
+    strict: bool,
+    config: &Config,
 ) -> Vec<ValidationIssue> {
     let mut issues = Vec::new();
-    let groups = required_groups(template);
-    let known = known_sections(template);
+    let known = rules.known_sections(config);
 
     let mut counts: HashMap<String, usize> = HashMap::new();
     for section in &note.sections {
@@ -59,30 +294,19 @@ pub fn validate_note(
         *counts.entry(key).or_insert(0) += 1;
     }
 
-    for group in groups {
-        let mut present = false;
-        for alias in &group {
-            let key = util::normalize_heading_key(alias);
-            if counts.get(&key).copied().unwrap_or(0) > 0 {
-                present = true;
-                break;
-            }
-        }
-        if !present {
+    for (group_idx, group) in rules.required.iter().enumerate() {
+        if find_group_section(note, group).is_none() {
             let severity = if strict {
                 Severity::Error
             } else {
-                Severity::Warn
+                rules.severity_for("missing_required", Severity::Warn)
             };
             issues.push(ValidationIssue {
                 code: "missing_required".to_string(),
-                message: format!(
-                    "Missing required section ({})",
-                    group.first().cloned().unwrap_or_default()
-                ),
+                message: format!("Missing required section ({})", group.section),
                 severity,
-                section: group.first().cloned(),
-                span: None,
+                section: Some(group.section.clone()),
+                span: expected_span(note, &rules.required, group_idx),
             });
         }
     }
@@ -93,9 +317,9 @@ pub fn validate_note(
             issues.push(ValidationIssue {
                 code: "duplicate_section".to_string(),
                 message: format!("Duplicate section '{}'", section.name),
-                severity: Severity::Warn,
+                severity: rules.severity_for("duplicate_section", Severity::Warn),
                 section: Some(section.name.clone()),
-                span: None,
+                span: Span::of_section(section),
             });
         }
 
@@ -103,27 +327,200 @@ pub fn validate_note(
             issues.push(ValidationIssue {
                 code: "unknown_section".to_string(),
                 message: format!("Unknown section '{}'", section.name),
-                severity: Severity::Info,
+                severity: rules.severity_for("unknown_section", Severity::Info),
+                section: Some(section.name.clone()),
+                span: Span::of_section(section),
+            });
+        }
+
+        let allergy_status = (key == section_key(&SectionName::Allergies))
+            .then(|| note.allergies.as_ref().and_then(|found| found.status));
+        if let Some(None) = allergy_status {
+            issues.push(ValidationIssue {
+                code: "allergy_status_missing".to_string(),
+                message: format!(
+                    "Section '{}' lists no allergies and no explicit status (e.g. NKDA)",
+                    section.name
+                ),
+                severity: rules.severity_for("allergy_status_missing", Severity::Warn),
                 section: Some(section.name.clone()),
-                span: None,
+                span: Span::of_section(section),
             });
         }
 
         let trimmed = section.content.trim();
-        if trimmed.is_empty() || trimmed.len() < MIN_SECTION_LEN {
+        let explicit = matches!(allergy_status, Some(Some(_)));
+        if !explicit && (trimmed.is_empty() || trimmed.len() < rules.min_length_for(&section.name))
+        {
             issues.push(ValidationIssue {
                 code: "section_too_short".to_string(),
                 message: format!("Section '{}' is empty or too short", section.name),
-                severity: Severity::Warn,
+                severity: rules.severity_for("section_too_short", Severity::Warn),
                 section: Some(section.name.clone()),
-                span: None,
+                span: Span::of_section(section),
             });
         }
+
+        if let Some(max) = rules.max_length_for(&section.name) {
+            if trimmed.len() > max {
+                issues.push(ValidationIssue {
+                    code: "section_too_long".to_string(),
+                    message: format!("Section '{}' exceeds {} characters", section.name, max),
+                    severity: rules.severity_for("section_too_long", Severity::Warn),
+                    section: Some(section.name.clone()),
+                    span: Span::of_section(section),
+                });
+            }
+        }
+    }
+
+    issues.extend(ros_issues(note, rules));
+    issues.extend(vital_issues(note, rules));
+
+    for compiled in &rules.content {
+        let keys = [util::normalize_heading_key(&compiled.rule.section)];
+        for section in note
+            .sections
+            .iter()
+            .filter(|s| section_covers(&s.name, &keys))
+        {
+            issues.extend(content_issues(section, chunk, compiled, rules));
+        }
+    }
+
+    issues
+}
+
+fn vital_issues(note: &StructuredNote, rules: &RuleSet) -> Vec<ValidationIssue> {
+    if note.vitals.is_none() {
+        return Vec::new();
+    }
+    note.sections
+        .iter()
+        .filter(|s| vitals::is_vitals_section(&s.name))
+        .flat_map(|section| {
+            vitals::section_implausible(&section.content)
+                .into_iter()
+                .map(|message| ValidationIssue {
+                    code: "implausible_vital".to_string(),
+                    message,
+                    severity: rules.severity_for("implausible_vital", Severity::Warn),
+                    section: Some(section.name.clone()),
+                    span: Span::of_section(section),
+                })
+        })
+        .collect()
+}
+
+fn ros_issues(note: &StructuredNote, rules: &RuleSet) -> Option<ValidationIssue> {
+    if rules.ros_min_systems == 0 {
+        return None;
+    }
+    let key = section_key(&SectionName::Ros);
+    let section = note
+        .sections
+        .iter()
+        .find(|s| util::normalize_heading_key(&s.name) == key)?;
+    let (reviewed, all_other_negative) = note
+        .ros
+        .as_ref()
+        .map(|ros| (ros.systems_reviewed(), ros.all_other_negative))
+        .unwrap_or_default();
+    if all_other_negative || reviewed >= rules.ros_min_systems {
+        return None;
     }
+    Some(ValidationIssue {
+        code: "ros_incomplete".to_string(),
+        message: format!(
+            "Section '{}' covers {} of at least {} systems",
+            section.name, reviewed, rules.ros_min_systems
+        ),
+        severity: rules.severity_for("ros_incomplete", Severity::Warn),
+        section: Some(section.name.clone()),
+        span: Span::of_section(section),
+    })
+}
 
+fn content_issues(
+    section: &Section,
+    chunk: Option<&NoteChunk>,
+    compiled: &CompiledRule,
+    rules: &RuleSet,
+) -> Vec<ValidationIssue> {
+    let rule = &compiled.rule;
+    let mut issues = Vec::new();
+    for check in &compiled.checks {
+        let (message, span) = match check {
+            ContentCheck::Require(re) if !re.is_match(&section.content) => (
+                format!("Section '{}' is missing expected content", section.name),
+                Span::of_section(section),
+            ),
+            ContentCheck::Forbid(re) => match re.find(&section.content) {
+                Some(found) => (
+                    format!(
+                        "Section '{}' contains disallowed content '{}'",
+                        section.name,
+                        found.as_str().trim()
+                    ),
+                    match_span(section, chunk, found.as_str().trim())
+                        .or_else(|| Span::of_section(section)),
+                ),
+                None => continue,
+            },
+            ContentCheck::Require(_) => continue,
+        };
+        issues.push(ValidationIssue {
+            code: rule.code.clone(),
+            message: rule.message.clone().unwrap_or(message),
+            severity: rules.severity_for(&rule.code, rule.severity),
+            section: Some(section.name.clone()),
+            span,
+        });
+    }
     issues
 }
 
+fn match_span(section: &Section, chunk: Option<&NoteChunk>, needle: &str) -> Option<Span> {
+    let found = SectionCursor::new(chunk?, section)?.find(needle)?;
+    Some(Span {
+        line_start: found.line_start,
+        line_end: found.line_end,
+    })
+}
+
+fn find_group_section<'a>(note: &'a StructuredNote, group: &RequiredGroup) -> Option<&'a Section> {
+    let keys: Vec<String> = group.names().map(util::normalize_heading_key).collect();
+    note.sections
+        .iter()
+        .find(|section| section_covers(&section.name, &keys))
+}
+
+fn section_covers(name: &str, keys: &[String]) -> bool {
+    if keys.contains(&util::normalize_heading_key(name)) {
+        return true;
+    }
+    sections::combined_parts(name)
+        .is_some_and(|parts| parts.iter().any(|part| keys.contains(&section_key(part))))
+}
+
+fn section_key(name: &SectionName) -> String {
+    util::normalize_heading_key(name.as_str())
+}
+
+fn expected_span(note: &StructuredNote, groups: &[RequiredGroup], missing: usize) -> Option<Span> {
+    let before = groups[..missing]
+        .iter()
+        .rev()
+        .find_map(|group| find_group_section(note, group).and_then(|s| s.span));
+    if let Some(span) = before {
+        return Some(Span::at_line(span.line_end));
+    }
+    groups[missing + 1..]
+        .iter()
+        .find_map(|group| find_group_section(note, group).and_then(|s| s.span))
+        .map(|span| Span::at_line(span.line_start))
+}
+
 pub fn summarize_sections(note: &StructuredNote) -> Vec<SectionSummary> {
     note.sections
         .iter()
@@ -137,81 +534,3 @@ pub fn summarize_sections(note: &StructuredNote) -> Vec<SectionSummary> {
         })
         .collect()
 }
-
-fn required_groups(template: Template) -> Vec<Vec<String>> {
-    match template {
-        Template::Soap => vec![
-            vec!["Subjective".to_string(), "S".to_string()],
-            vec!["Objective".to_string(), "O".to_string()],
-            vec![
-                "Assessment".to_string(),
-                "A".to_string(),
-                "Diagnosis".to_string(),
-                "Dx".to_string(),
-            ],
-            vec!["Plan".to_string(), "P".to_string()],
-        ],
-        Template::Hp => vec![
-            vec!["HPI".to_string(), "History of Present Illness".to_string()],
-            vec![
-                "PMH".to_string(),
-                "Past Medical History".to_string(),
-                "Hx".to_string(),
-            ],
-            vec!["Medications".to_string(), "Meds".to_string()],
-            vec!["Allergies".to_string(), "Allergy".to_string()],
-            vec![
-                "Physical Exam".to_string(),
-                "Exam".to_string(),
-                "PE".to_string(),
-            ],
-            vec![
-                "Assessment".to_string(),
-                "Dx".to_string(),
-                "Diagnosis".to_string(),
-            ],
-            vec!["Plan".to_string(), "P".to_string()],
-        ],
-        Template::Discharge => vec![
-            vec![
-                "Admission Dx".to_string(),
-                "Discharge Dx".to_string(),
-                "Diagnoses".to_string(),
-                "Diagnosis".to_string(),
-            ],
-            vec![
-                "Hospital Course".to_string(),
-                "HospitalCourse".to_string(),
-                "Course".to_string(),
-            ],
-            vec![
-                "Medications".to_string(),
-                "Discharge Meds".to_string(),
-                "DischargeMeds".to_string(),
-            ],
-            vec![
-                "Follow-up".to_string(),
-                "Follow Up".to_string(),
-                "FollowUp".to_string(),
-            ],
-        ],
-    }
-}
-
-fn known_sections(template: Template) -> HashSet<String> {
-    let mut all = HashSet::new();
-    for group in required_groups(template) {
-        for name in group {
-            all.insert(util::normalize_heading_key(&name));
-        }
-    }
-    let optional = match template {
-        Template::Soap => vec!["Narrative"],
-        Template::Hp => vec!["Chief Complaint", "ROS", "Review of Systems", "Narrative"],
-        Template::Discharge => vec!["Disposition", "Instructions", "Narrative"],
-    };
-    for name in optional {
-        all.insert(util::normalize_heading_key(name));
-    }
-    all
-}
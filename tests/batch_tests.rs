@@ -1,6 +1,6 @@
 use clinote::cli::{run_batch, BatchArgs};
 use clinote::config::Config;
-use clinote::models::NoteFormat;
+use clinote::models::{FormatSelection, NoteFormat};
 use clinote::render::OutputFormat;
 use std::fs;
 
@@ -19,11 +19,12 @@ fn batch_continues_on_failure() {
     let args = BatchArgs {
         input_dir: input_dir.clone(),
         glob: Some("*.txt".to_string()),
-        format: NoteFormat::Soap,
+        format: NoteFormat::Soap.into(),
         out_dir: out_dir.clone(),
         out_format: OutputFormat::Json,
         config: None,
         bundle: None,
+        reproducible: false,
     };
 
     let report = run_batch(&args, &Config::default()).unwrap();
@@ -34,3 +35,43 @@ fn batch_continues_on_failure() {
 
     let _ = fs::remove_dir_all(&temp_dir);
 }
+
+#[test]
+fn batch_counts_notes_by_detected_format() {
+    let temp_dir = std::env::temp_dir().join("clinote_batch_formats_test");
+    let input_dir = temp_dir.join("in");
+    let out_dir = temp_dir.join("out");
+    let _ = fs::remove_dir_all(&temp_dir);
+    fs::create_dir_all(&input_dir).unwrap();
+
+    let bundle = [
+        fs::read_to_string("tests/fixtures/soap_messy.txt").unwrap(),
+        fs::read_to_string("tests/fixtures/hp_messy.txt").unwrap(),
+        fs::read_to_string("tests/fixtures/soap_messy.txt").unwrap(),
+    ]
+    .join("----- NOTE -----\n");
+    fs::write(input_dir.join("bundle.txt"), bundle).unwrap();
+
+    let args = BatchArgs {
+        input_dir: input_dir.clone(),
+        glob: Some("*.txt".to_string()),
+        format: FormatSelection::Auto,
+        out_dir: out_dir.clone(),
+        out_format: OutputFormat::Csv,
+        config: None,
+        bundle: None,
+        reproducible: true,
+    };
+
+    let report = run_batch(&args, &Config::default()).unwrap();
+    assert_eq!(report.counts_by_format["soap"].notes, 2);
+    assert_eq!(report.counts_by_format["hp"].notes, 1);
+    assert_eq!(report.counts_by_format["hp"].sections["Plan"], 1);
+    let csv = report.to_csv().unwrap();
+    assert!(csv.starts_with("format,notes,ambiguous,section,count"));
+    assert!(csv.contains("soap,2,0,Plan,2"));
+    let rendered = fs::read_to_string(out_dir.join("bundle.csv")).unwrap();
+    assert!(rendered.contains(",hp,"));
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
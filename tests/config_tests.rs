@@ -1,5 +1,5 @@
 use clinote::config::Config;
-use clinote::models::SectionName;
+use clinote::models::{NoteFormat, SectionName};
 
 fn full_config_toml() -> String {
     r#"
@@ -48,7 +48,7 @@ glob_default = "*.txt"
 
 #[test]
 fn parse_config_ok() {
-    let config: Config = toml::from_str(&full_config_toml()).unwrap();
+    let config = Config::from_toml_str(&full_config_toml()).unwrap();
     assert!(config.enable_fallback_heuristics);
     assert_eq!(
         config.formats.soap.section_order[0],
@@ -87,6 +87,133 @@ layout = "wide"
 
 glob_default = "*.txt"
 "#;
-    let result: Result<Config, _> = toml::from_str(toml_str);
+    let result = Config::from_toml_str(toml_str);
+    assert!(result.is_err());
+}
+
+#[test]
+fn parses_custom_formats() {
+    let toml_str = r#"
+[formats.progress]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+required_sections = ["Assessment", "Plan"]
+
+[formats.procedure]
+section_order = ["Chief Complaint", "Physical Exam", "Plan"]
+"#;
+    let config = Config::from_toml_str(toml_str).unwrap();
+    let names: Vec<String> = config
+        .formats
+        .names()
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["soap", "hp", "discharge", "procedure", "progress"]
+    );
+    let progress: NoteFormat = "progress".parse().unwrap();
+    assert_eq!(config.section_order(&progress).len(), 4);
+    assert_eq!(config.formats.soap.section_order.len(), 4);
+    assert!(config.ensure_format(&progress).is_ok());
+    assert!(config
+        .ensure_format(&NoteFormat::Custom("consult".to_string()))
+        .is_err());
+}
+
+#[test]
+fn custom_format_keys_are_case_insensitive() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.Progress]
+section_order = ["Subjective", "Plan"]
+"#,
+    )
+    .unwrap();
+    let progress: NoteFormat = "Progress".parse().unwrap();
+    assert!(config.ensure_format(&progress).is_ok());
+    assert_eq!(config.section_order(&progress).len(), 2);
+
+    let shadowing = Config::from_toml_str(
+        r#"
+[formats.Soap]
+section_order = ["Subjective"]
+"#,
+    )
+    .unwrap_err();
+    assert!(shadowing
+        .to_string()
+        .contains("shadows the built-in 'soap'"));
+
+    let duplicate = Config::from_toml_str(
+        r#"
+[formats.progress]
+section_order = ["Subjective"]
+
+[formats.PROGRESS]
+section_order = ["Plan"]
+"#,
+    )
+    .unwrap_err();
+    assert!(duplicate.to_string().contains("more than once"));
+}
+
+#[test]
+fn custom_sections_extend_registry() {
+    let toml_str = r#"
+[formats.soap]
+section_order = ["Subjective", "Vital Signs", "Objective", "Assessment", "Plan"]
+
+[formats.hp]
+section_order = ["HPI", "Social History", "Family History"]
+
+[sections."Vital Signs"]
+aliases = ["Vitals", "VS"]
+code = "8716-3"
+
+[sections."Social History"]
+aliases = ["SH", "Soc Hx"]
+label = "Social history"
+
+[sections."Family History"]
+aliases = ["FH"]
+"#;
+    let config = Config::from_toml_str(toml_str).unwrap();
+    assert_eq!(
+        config.formats.soap.section_order[1],
+        SectionName::Custom("Vital Signs".to_string())
+    );
+    assert_eq!(config.formats.hp.section_order[0], SectionName::Hpi);
+    let registry = clinote::sections::SectionRegistry::from_config(&config);
+    assert_eq!(
+        registry.get("social history").unwrap().label,
+        "Social history"
+    );
+    assert!(registry.contains("Plan"));
+}
+
+#[test]
+fn undeclared_custom_section_errors() {
+    let toml_str = r#"
+[formats.hp]
+section_order = ["HPI", "Social History"]
+"#;
+    let err = Config::from_toml_str(toml_str).unwrap_err();
+    assert!(err.to_string().contains("Social History"));
+}
+
+#[test]
+fn invalid_bundle_pattern_errors() {
+    let result = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective"]
+
+[bundle]
+mode_default = "auto"
+delimiters = []
+delimiter_patterns = ["(?P<date>"]
+"#,
+    );
     assert!(result.is_err());
 }
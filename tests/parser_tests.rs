@@ -1,5 +1,5 @@
 use clinote::config::Config;
-use clinote::models::{BundleMode, NoteFormat};
+use clinote::models::{BundleMode, FormatSelection, NoteFormat};
 use clinote::parser;
 use clinote::parser::headings;
 use clinote::parser::sectionize;
@@ -32,3 +32,738 @@ fn bundle_splits_on_delimiter() {
     let (notes, _warnings) = parser::split_bundle(text, BundleMode::On, &config);
     assert_eq!(notes.len(), 2);
 }
+
+#[test]
+fn note_ids_are_content_derived() {
+    let config = Config::default();
+    let options = parser::ParseOptions {
+        apply_heuristics: true,
+        ..Default::default()
+    };
+    let first = parser::parse_note(
+        "Subjective: cough",
+        NoteFormat::Soap,
+        &config,
+        Some("a.txt".to_string()),
+        1,
+        options,
+    );
+    let second = parser::parse_note(
+        "Subjective: cough\r\n",
+        NoteFormat::Soap,
+        &config,
+        Some("a.txt".to_string()),
+        1,
+        options,
+    );
+    let other = parser::parse_note(
+        "Subjective: fever",
+        NoteFormat::Soap,
+        &config,
+        Some("a.txt".to_string()),
+        1,
+        options,
+    );
+    assert_eq!(first.id, second.id);
+    assert_ne!(first.id, other.id);
+}
+
+#[test]
+fn custom_sections_flow_through_parsing() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Vital Signs", "Objective", "Assessment", "Plan"]
+
+[sections."Vital Signs"]
+aliases = ["Vitals"]
+label = "Vital signs"
+code = "8716-3"
+"#,
+    )
+    .unwrap();
+    let note = parser::parse_note(
+        "Subjective: cough\nVitals: BP 120/80\nPlan: fluids",
+        NoteFormat::Soap,
+        &config,
+        None,
+        1,
+        parser::ParseOptions {
+            apply_heuristics: true,
+            ..Default::default()
+        },
+    );
+    let names: Vec<&str> = note.sections.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["Subjective", "Vital Signs", "Plan"]);
+    assert_eq!(note.sections[1].label.as_deref(), Some("Vital signs"));
+    assert_eq!(note.sections[1].code.as_deref(), Some("8716-3"));
+}
+
+#[test]
+fn bundle_notes_are_classified_individually() {
+    let config = Config::default();
+    let text = [
+        std::fs::read_to_string("tests/fixtures/hp_messy.txt").unwrap(),
+        std::fs::read_to_string("tests/fixtures/soap_messy.txt").unwrap(),
+        std::fs::read_to_string("tests/fixtures/discharge_messy.txt").unwrap(),
+    ]
+    .join("----- NOTE -----\n");
+    let notes = parser::parse_notes(
+        &text,
+        FormatSelection::Auto,
+        &config,
+        None,
+        0,
+        parser::ParseOptions {
+            apply_heuristics: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let formats: Vec<NoteFormat> = notes.iter().map(|n| n.format.clone()).collect();
+    assert_eq!(
+        formats,
+        vec![NoteFormat::Hp, NoteFormat::Soap, NoteFormat::Discharge]
+    );
+    assert!(notes.iter().all(|n| n.metadata.format_detection.is_some()));
+}
+
+#[test]
+fn bundle_regex_delimiters_capture_fields() {
+    let mut config = Config::default();
+    config.bundle.delimiter_patterns = vec![
+        r"^Note ID: (?P<note_id>\d+) \| Author: (?P<author>[^|]+) \| (?P<date>\d{4}-\d{2}-\d{2} \d{2}:\d{2})$"
+            .to_string(),
+    ];
+    let text = "Note ID: 12345 | Author: Dr. Lee | 2024-03-01 08:15\nSubjective: cough\n\nNote ID: 12346 | Author: Dr. Park | 2024-03-02 09:00\nSubjective: better";
+    let (notes, _warnings) = parser::split_bundle(text, BundleMode::Auto, &config);
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].text, "Subjective: cough");
+    assert_eq!(notes[0].captures["author"], "Dr. Lee");
+    assert_eq!(notes[1].captures["date"], "2024-03-02 09:00");
+    assert_eq!(notes[1].captures["note_id"], "12346");
+    assert_eq!(notes[1].line_offset, 4);
+
+    config.bundle.delimiter_line = clinote::config::DelimiterLine::Keep;
+    let (notes, _warnings) = parser::split_bundle(text, BundleMode::Auto, &config);
+    assert!(notes[1].text.starts_with("Note ID: 12346"));
+    assert_eq!(notes[1].line_offset, 3);
+}
+
+#[test]
+fn bundle_splits_when_heading_sequence_restarts() {
+    let config = Config::default();
+    let text = "Subjective: cough\nSubjective: also wheeze\nPlan: fluids\n\nChief Complaint: chest pain\nHPI: two days\nPlan: ECG\nSubjective: follow-up visit\nPlan: rest";
+    let (notes, warnings) = parser::split_bundle(text, BundleMode::Auto, &config);
+    assert_eq!(notes.len(), 3);
+    assert_eq!(notes[1].line_offset, 4);
+    assert_eq!(notes[2].text, "Subjective: follow-up visit\nPlan: rest");
+    let strategy = warnings
+        .iter()
+        .find(|w| w.code == "bundle_strategy")
+        .unwrap();
+    assert!(strategy.message.contains("heading resets"));
+    let splits: Vec<usize> = warnings
+        .iter()
+        .filter(|w| w.code == "bundle_split")
+        .map(|w| w.line_start)
+        .collect();
+    assert_eq!(splits, vec![5, 8]);
+
+    let (notes, warnings) = parser::split_bundle(text, BundleMode::Dates, &config);
+    assert_eq!(notes.len(), 1);
+    assert!(warnings.iter().any(|w| w.code == "bundle_not_split"));
+}
+
+#[test]
+fn reordered_single_note_is_not_split_on_heading_resets() {
+    let config = Config::default();
+    let reordered = [
+        "Discharge Diagnosis: pneumonia\nAdmission Diagnosis: sepsis\nHospital Course: improved on antibiotics\nFollow-up: PCP in 1 week",
+        "Subjective: cough\nObjective: clear lungs\nAssessment: viral URI\nPlan: fluids\nChief Complaint: cough for 3 days",
+    ];
+    for text in reordered {
+        let (notes, warnings) = parser::split_bundle(text, BundleMode::Auto, &config);
+        assert_eq!(notes.len(), 1, "{}", text);
+        assert!(!warnings.iter().any(|w| w.code == "bundle_split"));
+    }
+}
+
+#[test]
+fn preamble_key_values_become_header() {
+    let mut config = Config::default();
+    config.header.fields.insert(
+        "mrn".to_string(),
+        vec!["MRN".to_string(), "Chart".to_string()],
+    );
+    config
+        .header
+        .fields
+        .insert("room".to_string(), vec!["Room".to_string()]);
+    let text = "Patient: Synthetic Demo 1\nDOB: 1990-01-01 | Chart: 00042\nProvider: Dr. Lee  Room: 12B\nNotes from triage\n\nSubjective: cough\nDate: not a header";
+    let note = parser::parse_note(
+        text,
+        NoteFormat::Soap,
+        &config,
+        None,
+        1,
+        parser::ParseOptions {
+            apply_heuristics: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(note.header["patient_name"], "Synthetic Demo 1");
+    assert_eq!(note.header["dob"], "1990-01-01");
+    assert_eq!(note.header["mrn"], "00042");
+    assert_eq!(note.header["provider"], "Dr. Lee");
+    assert_eq!(note.header["room"], "12B");
+    assert!(!note.header.contains_key("encounter_date"));
+    assert_eq!(note.sections[0].content, "cough\nDate: not a header");
+}
+
+#[test]
+fn fallback_headings_merge_with_regular_headings_per_line() {
+    let config = Config::default();
+    let input = std::fs::read_to_string("tests/fixtures/hp_messy.txt").unwrap();
+    let (candidates, warnings) = parser::extract_candidates(
+        &input,
+        NoteFormat::Hp,
+        &config,
+        parser::ParseOptions {
+            apply_heuristics: true,
+            ..Default::default()
+        },
+    );
+    let cc = candidates
+        .iter()
+        .find(|c| c.name == "Chief Complaint")
+        .unwrap();
+    assert_eq!(cc.content, "chest discomfort");
+    assert!(cc.confidence < 0.7);
+    let pmh = candidates.iter().find(|c| c.name == "PMH").unwrap();
+    assert!(pmh.confidence > 0.8);
+    let used: Vec<usize> = warnings
+        .iter()
+        .filter(|w| w.code == "fallback_heading_used")
+        .map(|w| w.line_start)
+        .collect();
+    assert_eq!(used, vec![1]);
+
+    let (candidates, _warnings) = parser::extract_candidates(
+        &input,
+        NoteFormat::Hp,
+        &config,
+        parser::ParseOptions::default(),
+    );
+    assert!(!candidates.iter().any(|c| c.name == "Chief Complaint"));
+}
+
+#[test]
+fn fallback_per_line_can_be_turned_off() {
+    let config = Config {
+        fallback_per_line: false,
+        ..Config::default()
+    };
+    let input = "Hospital Course:\nAdmitted for pneumonia.\nCourse - uneventful\nDischarge Diagnosis:\nPneumonia";
+    let (candidates, warnings) = parser::extract_candidates(
+        input,
+        NoteFormat::Discharge,
+        &config,
+        parser::ParseOptions {
+            apply_heuristics: true,
+            ..Default::default()
+        },
+    );
+    let course = candidates
+        .iter()
+        .find(|c| c.name == "Hospital Course")
+        .unwrap();
+    assert_eq!(
+        course.content,
+        "Admitted for pneumonia.\nCourse - uneventful"
+    );
+    assert!(!warnings.iter().any(|w| w.code == "fallback_heading_used"));
+}
+
+#[test]
+fn inline_headings_split_one_line_into_sections() {
+    let config = Config::default();
+    let input = "S: cough x3d O: T 38.1 A: URI P: fluids";
+    let (candidates, _warnings) = parser::extract_candidates(
+        input,
+        NoteFormat::Soap,
+        &config,
+        parser::ParseOptions::default(),
+    );
+    let contents: Vec<(&str, &str)> = candidates
+        .iter()
+        .map(|c| (c.name.as_str(), c.content.as_str()))
+        .collect();
+    assert_eq!(
+        contents,
+        vec![
+            ("Subjective", "cough x3d"),
+            ("Objective", "T 38.1"),
+            ("Assessment", "URI"),
+            ("Plan", "fluids"),
+        ]
+    );
+    let plan = candidates.iter().find(|c| c.name == "Plan").unwrap();
+    let heading = plan.heading_span.as_ref().unwrap();
+    assert_eq!(&input[heading.byte_start..heading.byte_end], "P:");
+
+    let prose = headings::match_headings("Plan: discuss A: B testing", &config);
+    assert_eq!(prose.len(), 1);
+    assert_eq!(
+        prose[0].inline_content.as_deref(),
+        Some("discuss A: B testing")
+    );
+}
+
+#[test]
+fn decorated_heading_styles_are_stripped_and_recorded() {
+    use clinote::models::HeadingStyle;
+
+    let config = Config::default();
+    let input = "## Subjective\ncough for three days\n**Objective:** T 38.1\n--- Assessment ---\nviral URI\nPlan\n====\nfluids and rest";
+    let (candidates, _warnings) = parser::extract_candidates(
+        input,
+        NoteFormat::Soap,
+        &config,
+        parser::ParseOptions::default(),
+    );
+    let found: Vec<(&str, &str, Option<HeadingStyle>)> = candidates
+        .iter()
+        .map(|c| (c.name.as_str(), c.content.as_str(), c.heading_style))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                "Subjective",
+                "cough for three days",
+                Some(HeadingStyle::Markdown)
+            ),
+            ("Objective", "T 38.1", Some(HeadingStyle::Bold)),
+            ("Assessment", "viral URI", Some(HeadingStyle::Dashed)),
+            ("Plan", "fluids and rest", Some(HeadingStyle::Underlined)),
+        ]
+    );
+
+    let numbered = headings::match_heading("1. Assessment", &config).unwrap();
+    assert_eq!(numbered.heading, "Assessment");
+    assert_eq!(numbered.style, HeadingStyle::Numbered);
+    let slashed = headings::match_heading("SUBJECTIVE/OBJECTIVE", &config).unwrap();
+    assert_eq!(slashed.heading, "Subjective");
+    assert_eq!(slashed.style, HeadingStyle::Slash);
+    assert!(headings::match_heading("1. Continue lisinopril", &config).is_none());
+    assert!(headings::match_heading("1. Follow up: 2 weeks", &config).is_none());
+
+    let note = parser::parse_note(
+        "Chief Complaint: palpitations\nPlan:\n1. Follow up: 2 weeks\n2. ECG\n3. Follow-up",
+        NoteFormat::Hp,
+        &config,
+        None,
+        1,
+        parser::ParseOptions::default(),
+    );
+    let plan = note.sections.iter().find(|s| s.name == "Plan").unwrap();
+    assert_eq!(plan.content, "1. Follow up: 2 weeks\n2. ECG\n3. Follow-up");
+    assert!(note.sections.iter().all(|s| s.name != "Narrative"));
+    assert!(!note.warnings.iter().any(|w| w.code == "unmapped_heading"));
+
+    let mut plain = Config::default();
+    plain.heading_styles.clear();
+    assert!(headings::match_heading("## Subjective", &plain).is_none());
+}
+
+#[test]
+fn medications_section_becomes_structured_entries() {
+    use clinote::models::MedicationStatus;
+
+    let config = Config::default();
+    let text = "HPI: two days of pain\nMedications:\n- Lisinopril 10 mg PO daily - continue\n- Albuterol 90 mcg inhaled q4h PRN wheeze\n- STOP metformin 500 mg BID\nAspirin, clopidogrel\nPlan: NSAIDs";
+    let note = parser::parse_note(
+        text,
+        NoteFormat::Hp,
+        &config,
+        None,
+        1,
+        parser::ParseOptions::default(),
+    );
+    let names: Vec<&str> = note.medications.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Lisinopril",
+            "Albuterol",
+            "metformin",
+            "Aspirin",
+            "clopidogrel"
+        ]
+    );
+
+    let lisinopril = &note.medications[0];
+    assert_eq!(lisinopril.strength.as_deref(), Some("10"));
+    assert_eq!(lisinopril.unit.as_deref(), Some("mg"));
+    assert_eq!(lisinopril.route.as_deref(), Some("PO"));
+    assert_eq!(lisinopril.frequency.as_deref(), Some("daily"));
+    assert_eq!(lisinopril.status, Some(MedicationStatus::Continue));
+    let span = lisinopril.span.unwrap();
+    assert_eq!(span.line_start, 3);
+    assert_eq!(
+        &text[span.byte_start..span.byte_end],
+        "Lisinopril 10 mg PO daily - continue"
+    );
+
+    assert!(note.medications[1].prn);
+    assert_eq!(note.medications[2].status, Some(MedicationStatus::Stop));
+    assert_eq!(note.medications[4].span.unwrap().line_start, 6);
+
+    for meta in ["Home meds reviewed", "See MAR", "Med list unchanged"] {
+        assert!(
+            parser::medications::extract_medications(meta).is_empty(),
+            "{}",
+            meta
+        );
+    }
+}
+
+#[test]
+fn allergies_section_reports_entries_and_explicit_status() {
+    use clinote::models::AllergyStatus;
+    use clinote::parser::allergies;
+
+    let config = Config::default();
+    let text = "HPI: rash after antibiotics\nAllergies:\n- Penicillin (hives, severe)\n- Sulfa -> nausea\nLatex\nPlan: avoid beta-lactams";
+    let note = parser::parse_note(
+        text,
+        NoteFormat::Hp,
+        &config,
+        None,
+        1,
+        parser::ParseOptions::default(),
+    );
+    let found = note.allergies.expect("allergies parsed");
+    assert_eq!(found.status, Some(AllergyStatus::Listed));
+    let agents: Vec<&str> = found.entries.iter().map(|a| a.agent.as_str()).collect();
+    assert_eq!(agents, vec!["Penicillin", "Sulfa", "Latex"]);
+
+    let penicillin = &found.entries[0];
+    assert_eq!(penicillin.reaction.as_deref(), Some("hives"));
+    assert_eq!(penicillin.severity.as_deref(), Some("severe"));
+    let span = penicillin.span.unwrap();
+    assert_eq!(span.line_start, 3);
+    assert_eq!(
+        &text[span.byte_start..span.byte_end],
+        "Penicillin (hives, severe)"
+    );
+    assert_eq!(found.entries[1].reaction.as_deref(), Some("nausea"));
+
+    let nkda = allergies::extract_allergies("No known drug allergies. No food allergies reported.");
+    assert_eq!(nkda.status, Some(AllergyStatus::Nkda));
+    assert!(nkda.entries.is_empty());
+    assert_eq!(
+        allergies::extract_allergies("NKA").status,
+        Some(AllergyStatus::Nka)
+    );
+    assert_eq!(
+        allergies::extract_allergies("Unable to obtain").status,
+        Some(AllergyStatus::Unknown)
+    );
+    for phrase in ["not reviewed", "see chart", "pending"] {
+        let found = allergies::extract_allergies(phrase);
+        assert_eq!(found.status, None, "{}", phrase);
+        assert!(found.entries.is_empty(), "{}", phrase);
+    }
+    assert_eq!(
+        allergies::extract_allergies("Patient is not sure about past reactions").status,
+        None
+    );
+}
+
+#[test]
+fn vitals_are_normalized_to_canonical_units() {
+    let config = Config::default();
+    let text = "Subjective: cough\nObjective:\nBP 138/86, HR 92, RR 18, T 101.2F, SpO2 94% on RA\nWt 180 lbs, Ht 5'10\"\nAssessment: bronchitis\nPlan: rest";
+    let note = parser::parse_note(
+        text,
+        NoteFormat::Soap,
+        &config,
+        None,
+        1,
+        parser::ParseOptions::default(),
+    );
+    let vitals = note.vitals.expect("vitals parsed");
+    assert_eq!(vitals.bp_systolic, Some(138));
+    assert_eq!(vitals.bp_diastolic, Some(86));
+    assert_eq!(vitals.heart_rate, Some(92));
+    assert_eq!(vitals.respiratory_rate, Some(18));
+    assert_eq!(vitals.temperature_c, Some(38.4));
+    assert_eq!(vitals.spo2, Some(94));
+    assert_eq!(vitals.o2_delivery.as_deref(), Some("room air"));
+    assert_eq!(vitals.weight_kg, Some(81.6));
+    assert_eq!(vitals.height_cm, Some(177.8));
+
+    let metric = parser::vitals::extract_vitals("Temp 37.6C, weight 70 kg, height 1.65 m");
+    assert_eq!(metric.temperature_c, Some(37.6));
+    assert_eq!(metric.weight_kg, Some(70.0));
+    assert_eq!(metric.height_cm, Some(165.0));
+
+    let terse = parser::vitals::extract_vitals("Vitals: T 38 P 104 R 22\nTender at T12");
+    assert_eq!(terse.temperature_c, Some(38.0));
+    assert_eq!(terse.heart_rate, Some(104));
+    assert_eq!(terse.respiratory_rate, Some(22));
+    assert_eq!(
+        parser::vitals::extract_vitals("tender at T12").temperature_c,
+        None
+    );
+}
+
+#[test]
+fn vitals_take_the_earliest_reading_across_label_styles() {
+    let vitals = parser::vitals::extract_vitals(
+        "Vitals: BP 128/82 P 96 T 101.2 F\nRechecked later: Temp 37.2C, HR 80, BP 118/76",
+    );
+    assert_eq!(vitals.temperature_c, Some(38.4));
+    assert_eq!(vitals.heart_rate, Some(96));
+    assert_eq!(vitals.bp_systolic, Some(128));
+}
+
+#[test]
+fn labs_are_flagged_against_reference_ranges() {
+    use clinote::models::LabFlag;
+
+    let config = Config::default();
+    let text = "Subjective: fatigue\nObjective:\nNa 134, K 3.1 (L), Cr 1.4, glucose 182 mg/dL, Trop <0.01\n138 | 101 | 42\n-----+-----+------< 110\n5.6 | 19 | 2.8\nAssessment: AKI\nPlan: replete K 40";
+    let note = parser::parse_note(
+        text,
+        NoteFormat::Soap,
+        &config,
+        None,
+        1,
+        parser::ParseOptions::default(),
+    );
+    let analytes: Vec<&str> = note.labs.iter().map(|l| l.analyte.as_str()).collect();
+    assert_eq!(
+        analytes,
+        vec![
+            "Sodium",
+            "Potassium",
+            "Creatinine",
+            "Glucose",
+            "Troponin",
+            "Sodium",
+            "Chloride",
+            "BUN",
+            "Glucose",
+            "Potassium",
+            "Bicarbonate",
+            "Creatinine"
+        ]
+    );
+
+    let sodium = &note.labs[0];
+    assert_eq!(sodium.value, 134.0);
+    assert_eq!(sodium.unit.as_deref(), Some("mmol/L"));
+    assert_eq!(sodium.flag, Some(LabFlag::Low));
+    assert_eq!(note.labs[2].flag, Some(LabFlag::High));
+    assert_eq!(note.labs[4].comparator.as_deref(), Some("<"));
+    assert_eq!(note.labs[4].flag, Some(LabFlag::Normal));
+
+    let potassium = &note.labs[1];
+    assert_eq!(potassium.raw, "K 3.1 (L)");
+    let span = potassium.span.unwrap();
+    assert_eq!(span.line_start, 3);
+    assert_eq!(&text[span.byte_start..span.byte_end], "K 3.1 (L)");
+
+    let bicarbonate = &note.labs[10];
+    assert_eq!(bicarbonate.flag, Some(LabFlag::Low));
+    let span = bicarbonate.span.unwrap();
+    assert_eq!(span.line_start, 6);
+    assert_eq!(&text[span.byte_start..span.byte_end], "19");
+
+    let dictionary = clinote::parser::labs::LabDictionary::from_config(&config);
+    let doses = clinote::parser::labs::extract_labs(
+        "Given potassium 40 mEq PO and magnesium 2 g IV, K 3.4 after",
+        &dictionary,
+    );
+    let analytes: Vec<(&str, f64)> = doses
+        .iter()
+        .map(|l| (l.analyte.as_str(), l.value))
+        .collect();
+    assert_eq!(analytes, vec![("Potassium", 3.4)]);
+}
+
+#[test]
+fn lab_dictionary_comes_from_config() {
+    use clinote::models::LabFlag;
+    use clinote::parser::labs::{self, LabDictionary};
+
+    let config = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[labs.analytes.Ferritin]
+synonyms = ["Ferr"]
+unit = "ng/mL"
+low = 30
+high = 400
+
+[labs.analytes.Calcium]
+enabled = false
+
+[labs.analytes.sodium]
+high = 150
+"#,
+    )
+    .unwrap();
+    let dictionary = LabDictionary::from_config(&config);
+    let found = labs::extract_labs("Ferr 12, Ca 7.9, Na 148", &dictionary);
+    let analytes: Vec<&str> = found.iter().map(|l| l.analyte.as_str()).collect();
+    assert_eq!(analytes, vec!["Ferritin", "Sodium"]);
+    assert_eq!(found[0].flag, Some(LabFlag::Low));
+    assert_eq!(found[0].reference_low, Some(30.0));
+    assert_eq!(found[1].flag, Some(LabFlag::Normal));
+    assert_eq!(found[1].unit.as_deref(), Some("mmol/L"));
+    assert_eq!(
+        (found[1].reference_low, found[1].reference_high),
+        (Some(135.0), Some(150.0))
+    );
+    assert_eq!(
+        labs::extract_labs("Na+ 152", &dictionary)[0].flag,
+        Some(LabFlag::High)
+    );
+
+    let mismatched = labs::extract_labs(
+        "Glucose 5.4 mmol/L",
+        &LabDictionary::from_config(&Config::default()),
+    );
+    assert_eq!(mismatched[0].flag, None);
+    assert_eq!(mismatched[0].reference_high, None);
+}
+
+#[test]
+fn ros_findings_are_grouped_by_system_with_negation() {
+    use clinote::models::FindingStatus;
+
+    let config = Config::default();
+    let text = "Chief Complaint: chest pain\nROS:\nConstitutional: denies fever, chills, or weight loss. Reports fatigue.\nCV: + chest pain, no palpitations; possible orthopnea\nResp: negative\nPatient also reports headache but no dizziness\nAll other systems reviewed and negative.\nAssessment: angina";
+    let note = parser::parse_note(
+        text,
+        NoteFormat::Hp,
+        &config,
+        None,
+        1,
+        parser::ParseOptions::default(),
+    );
+    let ros = note.ros.expect("ros parsed");
+    assert!(ros.all_other_negative);
+    let systems: Vec<&str> = ros.systems.iter().map(|s| s.system.as_str()).collect();
+    assert_eq!(
+        systems,
+        vec![
+            "Constitutional",
+            "Cardiovascular",
+            "Respiratory",
+            "Neurological"
+        ]
+    );
+    let findings = |system: usize| -> Vec<(&str, FindingStatus)> {
+        ros.systems[system]
+            .findings
+            .iter()
+            .map(|f| (f.finding.as_str(), f.status))
+            .collect()
+    };
+    assert_eq!(
+        findings(0),
+        vec![
+            ("fever", FindingStatus::Absent),
+            ("chills", FindingStatus::Absent),
+            ("weight loss", FindingStatus::Absent),
+            ("fatigue", FindingStatus::Present),
+        ]
+    );
+    assert_eq!(
+        findings(1),
+        vec![
+            ("chest pain", FindingStatus::Present),
+            ("palpitations", FindingStatus::Absent),
+            ("orthopnea", FindingStatus::Uncertain),
+        ]
+    );
+    assert!(ros.systems[2].negative);
+    assert_eq!(
+        findings(3),
+        vec![
+            ("headache", FindingStatus::Present),
+            ("dizziness", FindingStatus::Absent),
+        ]
+    );
+    let note = parser::parse_note(
+        "ROS: Resp: cough, otherwise negative",
+        NoteFormat::Hp,
+        &config,
+        None,
+        1,
+        parser::ParseOptions::default(),
+    );
+    let ros = note.ros.expect("ros parsed");
+    assert!(ros.all_other_negative);
+    assert_eq!(ros.systems.len(), 1);
+    assert_eq!(ros.systems[0].system, "Respiratory");
+    assert_eq!(ros.systems[0].findings[0].finding, "cough");
+    assert_eq!(ros.systems[0].findings[0].status, FindingStatus::Present);
+}
+
+#[test]
+fn ros_label_does_not_override_a_mapped_finding_in_a_later_sentence() {
+    use clinote::models::FindingStatus;
+
+    let ros = parser::parse_note(
+        "ROS: GI: negative. Possible rash.",
+        NoteFormat::Hp,
+        &Config::default(),
+        None,
+        1,
+        parser::ParseOptions::default(),
+    )
+    .ros
+    .expect("ros parsed");
+    let gi = ros
+        .systems
+        .iter()
+        .find(|s| s.system == "Gastrointestinal")
+        .unwrap();
+    assert!(gi.negative);
+    assert!(gi.findings.is_empty());
+    let skin = ros.systems.iter().find(|s| s.system == "Skin").unwrap();
+    assert_eq!(skin.findings[0].finding, "rash");
+    assert_eq!(skin.findings[0].status, FindingStatus::Uncertain);
+}
+
+#[test]
+fn ros_system_overrides_merge_case_insensitively() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.hp]
+section_order = ["ROS"]
+
+[ros.systems.cv]
+findings = ["claudication"]
+
+[ros.systems.eyes]
+enabled = false
+"#,
+    )
+    .unwrap();
+    let systems = config.ros.resolved_systems();
+    assert!(!systems.contains_key("cv"));
+    assert!(!systems.contains_key("Eyes"));
+    let cardio = &systems["Cardiovascular"];
+    assert!(cardio.aliases.iter().any(|a| a == "CV"));
+    assert!(cardio.findings.iter().any(|f| f == "chest pain"));
+    assert!(cardio.findings.iter().any(|f| f == "claudication"));
+}
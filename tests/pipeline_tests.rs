@@ -0,0 +1,406 @@
+use clinote::config::Config;
+use clinote::models::{CsvLayout, FormatSelection, NoteFormat, SectionCandidate};
+use clinote::parser;
+use clinote::pipeline::{Pipeline, PipelineHooks, PipelineOptions};
+use clinote::render::{self, OutputFormat};
+use clinote::util;
+
+struct DropPlan;
+
+impl PipelineHooks for DropPlan {
+    fn review_candidates(
+        &self,
+        _note_index: usize,
+        candidates: Vec<SectionCandidate>,
+    ) -> anyhow::Result<Vec<SectionCandidate>> {
+        Ok(candidates
+            .into_iter()
+            .filter(|c| c.name != "Plan")
+            .collect())
+    }
+}
+
+#[test]
+fn pipeline_parses_bundle_and_validates_each_note() {
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let text = "Subjective: cough\nPlan: fluids\n----- NOTE -----\nSubjective: fever";
+    let output = Pipeline::new(config, options)
+        .parse_str(text, Some("bundle.txt".to_string()))
+        .unwrap();
+    assert_eq!(output.notes.len(), 2);
+    assert_eq!(output.reports.len(), 2);
+    assert_eq!(output.reports[1].note_index, 2);
+    assert!(output.issues().any(|i| i.code == "missing_required"));
+}
+
+#[test]
+fn pipeline_strict_reports_errors() {
+    let config = Config::default();
+    let mut options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    options.strict = true;
+    let output = Pipeline::new(config, options)
+        .parse_reader("Subjective: short".as_bytes(), None)
+        .unwrap();
+    assert!(output.has_errors());
+}
+
+#[test]
+fn pipeline_runs_candidate_reviewer() {
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let output = Pipeline::new(config, options)
+        .with_hooks(DropPlan)
+        .parse_str("Subjective: cough\nPlan: fluids", None)
+        .unwrap();
+    let names: Vec<&str> = output.notes[0]
+        .sections
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["Subjective"]);
+}
+
+#[test]
+fn reproducible_mode_gives_identical_json() {
+    let config = Config::default();
+    let mut options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    options.reproducible = true;
+    let pipeline = Pipeline::new(config, options);
+    let text = "Subjective: cough\nPlan: fluids";
+    let render = || {
+        let output = pipeline.parse_str(text, Some("a.txt".to_string())).unwrap();
+        render::render_notes(&output.notes, OutputFormat::Json, CsvLayout::Wide).unwrap()
+    };
+    let first = render();
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    assert_eq!(first, render());
+}
+
+#[test]
+fn library_parse_honors_reproducible_option() {
+    let config = Config::default();
+    let options = parser::ParseOptions {
+        reproducible: true,
+        ..Default::default()
+    };
+    let text = "Subjective: cough\n----- NOTE -----\nSubjective: fever";
+    let single = parser::parse_note(text, NoteFormat::Soap, &config, None, 1, options);
+    let bundle = parser::parse_notes(text, NoteFormat::Soap, &config, None, 0, options).unwrap();
+    assert_eq!(single.metadata.generated_at, util::timestamp(true));
+    assert!(bundle
+        .iter()
+        .all(|n| n.metadata.generated_at == util::timestamp(true)));
+}
+
+#[test]
+fn section_spans_point_into_whole_file() {
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let text =
+        "Subjective: cough\r\n----- NOTE -----\r\n\r\n\tSubjective: fever\r\nPlan:\r\nfluids\r\n";
+    let output = Pipeline::new(config, options)
+        .parse_str(text, None)
+        .unwrap();
+    let second = &output.notes[1];
+
+    let subjective = &second.sections[0];
+    let heading = subjective.heading_span.unwrap();
+    assert_eq!(heading.line_start, 4);
+    assert_eq!(&text[heading.byte_start..heading.byte_end], "Subjective:");
+
+    let plan = &second.sections[1];
+    let span = plan.span.unwrap();
+    assert_eq!((span.line_start, span.line_end), (5, 6));
+    assert_eq!(&text[span.byte_start..span.byte_end], "Plan:\r\nfluids");
+    let chars: String = text
+        .chars()
+        .skip(span.char_start)
+        .take(span.char_end - span.char_start)
+        .collect();
+    assert_eq!(chars, "Plan:\r\nfluids");
+}
+
+#[test]
+fn auto_format_picks_best_scoring_template() {
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, FormatSelection::Auto);
+    let pipeline = Pipeline::new(config, options);
+
+    let hp = std::fs::read_to_string("tests/fixtures/hp_messy.txt").unwrap();
+    let output = pipeline.parse_str(&hp, None).unwrap();
+    let note = &output.notes[0];
+    assert_eq!(note.format, NoteFormat::Hp);
+    let detection = note.metadata.format_detection.as_ref().unwrap();
+    assert_eq!(detection.format, NoteFormat::Hp);
+    assert!(detection.runner_up.is_some());
+    assert!(!detection.ambiguous);
+
+    let output = pipeline
+        .parse_str("Assessment: viral illness\nPlan: fluids and rest", None)
+        .unwrap();
+    let note = &output.notes[0];
+    let detection = note.metadata.format_detection.as_ref().unwrap();
+    assert!(detection.ambiguous);
+    assert!(detection.margin < 1.0);
+    assert!(note.warnings.iter().any(|w| w.code == "format_ambiguous"));
+}
+
+#[test]
+fn fixed_format_records_no_detection() {
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let output = Pipeline::new(config, options)
+        .parse_str("Subjective: cough", None)
+        .unwrap();
+    assert!(output.notes[0].metadata.format_detection.is_none());
+    assert!("auto".parse::<NoteFormat>().is_err());
+    assert_eq!(
+        "AUTO".parse::<FormatSelection>().unwrap(),
+        FormatSelection::Auto
+    );
+}
+
+#[test]
+fn mixed_bundle_validates_each_note_against_its_own_template() {
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, FormatSelection::Auto);
+    let text = [
+        std::fs::read_to_string("tests/fixtures/hp_messy.txt").unwrap(),
+        std::fs::read_to_string("tests/fixtures/soap_messy.txt").unwrap(),
+    ]
+    .join("----- NOTE -----\n");
+    let output = Pipeline::new(config, options)
+        .parse_str(&text, None)
+        .unwrap();
+    assert_eq!(output.notes[0].format, NoteFormat::Hp);
+    assert_eq!(output.notes[1].format, NoteFormat::Soap);
+    assert!(!output.reports[0]
+        .issues
+        .iter()
+        .any(|i| i.code == "missing_required" && i.section.as_deref() == Some("Subjective")));
+    assert!(!output.reports[1]
+        .issues
+        .iter()
+        .any(|i| i.code == "missing_required"));
+}
+
+#[test]
+fn delimiter_captures_land_in_note_metadata() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[bundle]
+mode_default = "auto"
+delimiters = []
+delimiter_patterns = ['^Note ID: (?P<note_id>\d+) \| Author: (?P<author>[^|]+) \| (?P<date>\S+)']
+"#,
+    )
+    .unwrap();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let text = "Note ID: 7 | Author: Dr. Lee | 2024-03-01\nSubjective: cough";
+    let output = Pipeline::new(config, options)
+        .parse_str(text, None)
+        .unwrap();
+    assert_eq!(output.notes.len(), 1);
+    let captures = &output.notes[0].metadata.delimiter_captures;
+    assert_eq!(captures["note_id"], "7");
+    assert_eq!(captures["date"], "2024-03-01");
+    let span = output.notes[0].sections[0].span.unwrap();
+    assert_eq!(span.line_start, 2);
+    let encounter = output.notes[0].encounter.as_ref().unwrap();
+    assert_eq!(encounter.date_of_service.as_deref(), Some("2024-03-01"));
+    assert_eq!(
+        encounter.header_line.as_deref(),
+        Some("Note ID: 7 | Author: Dr. Lee | 2024-03-01")
+    );
+
+    let json = render::render_notes(&output.notes, OutputFormat::Json, CsvLayout::Wide).unwrap();
+    assert!(json.contains("\"author\": \"Dr. Lee\""));
+}
+
+#[test]
+fn date_split_notes_carry_encounter_metadata() {
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let text = "2024-01-05 Clinic visit\nSubjective: cough\n\n01/19/2024 Follow-up\nSubjective: better\nPlan: no changes";
+    let output = Pipeline::new(config, options)
+        .parse_str(text, None)
+        .unwrap();
+    assert_eq!(output.notes.len(), 2);
+
+    let first = output.notes[0].encounter.as_ref().unwrap();
+    assert_eq!(first.date_of_service.as_deref(), Some("2024-01-05"));
+    assert_eq!(
+        first.header_line.as_deref(),
+        Some("2024-01-05 Clinic visit")
+    );
+    assert_eq!((first.position, first.bundle_size), (1, 2));
+    assert_eq!((first.line_start, first.line_end), (1, 2));
+
+    let second = output.notes[1].encounter.as_ref().unwrap();
+    assert_eq!(second.date_of_service.as_deref(), Some("2024-01-19"));
+    assert_eq!((second.line_start, second.line_end), (4, 6));
+
+    let wide = render::render_notes(&output.notes, OutputFormat::Csv, CsvLayout::Wide).unwrap();
+    let header = wide.lines().next().unwrap();
+    assert!(header.starts_with("id,format,source_file,note_index,encounter_position,encounter_total,encounter_line_start,encounter_line_end,date_of_service,encounter_header"));
+    let long = render::render_notes(&output.notes, OutputFormat::Csv, CsvLayout::Long).unwrap();
+    assert!(long.contains(",2,2,4,6,2024-01-19,01/19/2024 Follow-up,Subjective,better"));
+}
+
+#[test]
+fn unbundled_notes_have_no_encounter_columns() {
+    let text = std::fs::read_to_string("tests/fixtures/soap_messy.txt").unwrap();
+    let config = Config::default();
+    let mut options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    options.bundle_mode = clinote::models::BundleMode::Off;
+    let output = Pipeline::new(config, options)
+        .parse_str(&text, None)
+        .unwrap();
+    assert!(output.notes[0].encounter.is_none());
+    for layout in [CsvLayout::Wide, CsvLayout::Long] {
+        let csv = render::render_notes(&output.notes, OutputFormat::Csv, layout).unwrap();
+        let header = csv.lines().next().unwrap();
+        assert!(!header.contains("encounter_"));
+        assert!(!header.contains("date_of_service"));
+    }
+}
+
+#[test]
+fn unmapped_section_policy_controls_foreign_headings() {
+    let hp = std::fs::read_to_string("tests/fixtures/hp_messy.txt").unwrap();
+
+    let mut config = Config::default();
+    config.unmapped_sections = clinote::config::UnmappedPolicy::Keep;
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let output = Pipeline::new(config, options).parse_str(&hp, None).unwrap();
+    let names: Vec<&str> = output.notes[0]
+        .sections
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert_eq!(&names[..2], &["Assessment", "Plan"]);
+    assert!(names.contains(&"HPI"));
+    assert!(names.contains(&"PMH"));
+    assert!(!names.contains(&"Narrative"));
+    assert!(output.reports[0]
+        .issues
+        .iter()
+        .any(|i| i.code == "unknown_section" && i.section.as_deref() == Some("HPI")));
+
+    let mut config = Config::default();
+    config.unmapped_sections = clinote::config::UnmappedPolicy::Drop;
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let output = Pipeline::new(config, options).parse_str(&hp, None).unwrap();
+    let names: Vec<&str> = output.notes[0]
+        .sections
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["Assessment", "Plan"]);
+    assert!(output.notes[0]
+        .warnings
+        .iter()
+        .any(|w| w.code == "unmapped_heading" && w.message.ends_with("dropped")));
+}
+
+#[test]
+fn fuzzy_headings_match_typos_outside_strict_mode() {
+    let input = "CC: chest pain\nHistroy of Present Illness: two days of pain\nPhyiscal Exam: clear lungs\nAsessment: costochondritis\nPlan: NSAIDs";
+
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Hp);
+    let output = Pipeline::new(config, options)
+        .parse_str(input, None)
+        .unwrap();
+    let note = &output.notes[0];
+    let exam = note
+        .sections
+        .iter()
+        .find(|s| s.name == "Physical Exam")
+        .unwrap();
+    assert_eq!(exam.content, "clear lungs");
+    assert!(exam.confidence < 0.8);
+    assert!(note.sections.iter().any(|s| s.name == "HPI"));
+    let fuzzy: Vec<&str> = note
+        .warnings
+        .iter()
+        .filter(|w| w.code == "fuzzy_heading")
+        .map(|w| w.message.as_str())
+        .collect();
+    assert_eq!(fuzzy.len(), 3);
+    assert!(fuzzy
+        .iter()
+        .any(|m| m.starts_with("Heading 'Asessment' fuzzy-matched to 'Assessment'")));
+
+    let config = Config::default();
+    let mut options = PipelineOptions::from_config(&config, NoteFormat::Hp);
+    options.strict = true;
+    let output = Pipeline::new(config, options)
+        .parse_str(input, None)
+        .unwrap();
+    let note = &output.notes[0];
+    assert!(!note.sections.iter().any(|s| s.name == "Physical Exam"));
+    assert!(!note.warnings.iter().any(|w| w.code == "fuzzy_heading"));
+}
+
+#[test]
+fn assessment_and_plan_splits_or_stays_combined_with_problems() {
+    let input = "Subjective: cough\nObjective: T 38.1\nA/P:\n#1 HTN - continue lisinopril; recheck BMP\n#2 URI\n- fluids\n- rest";
+
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let output = Pipeline::new(config, options)
+        .parse_str(input, None)
+        .unwrap();
+    let note = &output.notes[0];
+    let names: Vec<&str> = note.sections.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, vec!["Subjective", "Objective", "Assessment", "Plan"]);
+    assert!(note.warnings.iter().any(|w| w.code == "combined_section"));
+    assert_eq!(note.problems.len(), 2);
+    assert_eq!(note.problems[0].number, Some(1));
+    assert_eq!(note.problems[0].title, "HTN");
+    assert_eq!(
+        note.problems[0].plan,
+        vec!["continue lisinopril", "recheck BMP"]
+    );
+    assert_eq!(note.problems[1].plan, vec!["fluids", "rest"]);
+
+    let csv = render::render_notes(&output.notes, OutputFormat::Csv, CsvLayout::Long).unwrap();
+    assert!(csv.lines().any(|l| l.ends_with(",Problem 1,HTN")));
+    assert!(csv.lines().any(|l| l.ends_with(",Problem 2 Plan,rest")));
+
+    let mut config = Config::default();
+    config.assessment_plan = clinote::config::AssessmentPlanMode::Combined;
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let output = Pipeline::new(config, options)
+        .parse_str(input, None)
+        .unwrap();
+    let names: Vec<&str> = output.notes[0]
+        .sections
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["Subjective", "Objective", "Assessment and Plan"]
+    );
+    assert_eq!(output.notes[0].problems.len(), 2);
+    assert!(!output
+        .issues()
+        .any(|i| i.code == "missing_required" || i.code == "unknown_section"));
+}
+
+#[test]
+fn plain_assessment_differential_is_not_a_problem_list() {
+    let input = "Subjective: sore throat\nObjective: T 37.9\nAssessment:\n1. viral URI\n2. strep pharyngitis\nPlan: rapid strep, fluids";
+    let config = Config::default();
+    let options = PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let output = Pipeline::new(config, options)
+        .parse_str(input, None)
+        .unwrap();
+    assert!(output.notes[0].problems.is_empty());
+}
@@ -9,14 +9,21 @@ fn sample_note() -> StructuredNote {
         note_index: 1,
         sections: vec![Section {
             name: "Subjective".to_string(),
+            label: None,
+            code: None,
             content: "Synthetic subjective content".to_string(),
             confidence: 0.9,
+            span: None,
+            heading_span: None,
+            heading_style: None,
         }],
         warnings: Vec::new(),
         metadata: Metadata {
             generated_at: "2024-01-01T00:00:00Z".to_string(),
             tool_version: "0.1.0".to_string(),
+            ..Default::default()
         },
+        ..Default::default()
     }
 }
 
@@ -41,3 +48,132 @@ fn renders_csv_wide() {
     let output = render::render_notes(&[note], OutputFormat::Csv, CsvLayout::Wide).unwrap();
     assert!(output.contains("Subjective"));
 }
+
+#[test]
+fn renders_header_fields() {
+    let mut note = sample_note();
+    note.header
+        .insert("patient_name".to_string(), "Synthetic Demo 1".to_string());
+    let csv = render::render_notes(
+        std::slice::from_ref(&note),
+        OutputFormat::Csv,
+        CsvLayout::Long,
+    )
+    .unwrap();
+    assert!(csv.lines().next().unwrap().contains("header_patient_name"));
+    assert!(csv.contains("Synthetic Demo 1"));
+    let md = render::render_notes(&[note], OutputFormat::Md, CsvLayout::Wide).unwrap();
+    assert!(md.contains("- patient_name: Synthetic Demo 1"));
+}
+
+#[test]
+fn renders_medications_table() {
+    let mut note = sample_note();
+    assert!(render::render_tables(std::slice::from_ref(&note))
+        .unwrap()
+        .is_empty());
+    note.medications.push(clinote::models::Medication {
+        name: "Lisinopril".to_string(),
+        strength: Some("10".to_string()),
+        unit: Some("mg".to_string()),
+        section: "Medications".to_string(),
+        raw: "Lisinopril 10 mg".to_string(),
+        ..Default::default()
+    });
+    let tables = render::render_tables(&[note]).unwrap();
+    assert_eq!(tables[0].0, "medications");
+    let mut lines = tables[0].1.lines();
+    assert!(lines.next().unwrap().starts_with("note_id,source_file"));
+    assert!(lines
+        .next()
+        .unwrap()
+        .contains("Medications,Lisinopril,10,mg,,,false,"));
+}
+
+#[test]
+fn renders_vitals_as_wide_columns() {
+    use clinote::models::Vitals;
+
+    let mut note = sample_note();
+    let plain = render::render_notes(
+        std::slice::from_ref(&note),
+        OutputFormat::Csv,
+        CsvLayout::Wide,
+    )
+    .unwrap();
+    assert!(!plain.contains("vitals_"));
+
+    note.vitals = Some(Vitals {
+        heart_rate: Some(72),
+        temperature_c: Some(37.2),
+        ..Default::default()
+    });
+    let csv = render::render_notes(&[note], OutputFormat::Csv, CsvLayout::Wide).unwrap();
+    let mut lines = csv.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let column = |name: &str| header.iter().position(|h| *h == name).unwrap();
+    assert_eq!(row[column("vitals_heart_rate")], "72");
+    assert_eq!(row[column("vitals_temperature_c")], "37.2");
+    assert_eq!(row[column("vitals_bp_systolic")], "");
+}
+
+#[test]
+fn renders_labs_table() {
+    use clinote::models::{LabFlag, LabResult};
+
+    let mut note = sample_note();
+    assert!(render::render_tables(std::slice::from_ref(&note))
+        .unwrap()
+        .is_empty());
+    note.labs.push(LabResult {
+        analyte: "Potassium".to_string(),
+        name: "K".to_string(),
+        value: 3.1,
+        unit: Some("mmol/L".to_string()),
+        reference_low: Some(3.5),
+        reference_high: Some(5.0),
+        flag: Some(LabFlag::Low),
+        section: "Objective".to_string(),
+        raw: "K 3.1".to_string(),
+        ..Default::default()
+    });
+    let tables = render::render_tables(&[note]).unwrap();
+    let (name, csv) = &tables[0];
+    assert_eq!(*name, "labs");
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("note_id,source_file,note_index,section,analyte"));
+    assert_eq!(
+        lines.next().unwrap(),
+        "note-1,input.txt,1,Objective,Potassium,K,,3.1,mmol/L,3.5,5,low,,,K 3.1"
+    );
+}
+
+#[test]
+fn renders_encounter_columns_only_with_encounters() {
+    let mut note = sample_note();
+    for layout in [CsvLayout::Wide, CsvLayout::Long] {
+        let csv =
+            render::render_notes(std::slice::from_ref(&note), OutputFormat::Csv, layout).unwrap();
+        assert!(!csv.lines().next().unwrap().contains("encounter_"));
+    }
+
+    note.encounter = Some(clinote::models::Encounter {
+        position: 1,
+        bundle_size: 2,
+        ..Default::default()
+    });
+    let csv = render::render_notes(&[note], OutputFormat::Csv, CsvLayout::Long).unwrap();
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("note_id,format,source_file,note_index,encounter_position,encounter_total"));
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("note-1,soap,input.txt,1,1,2,"));
+}
@@ -19,29 +19,37 @@ fn make_note(format: NoteFormat, sections: Vec<(&str, &str)>) -> StructuredNote
             .into_iter()
             .map(|(name, content)| Section {
                 name: name.to_string(),
+                label: None,
+                code: None,
                 content: content.to_string(),
                 confidence: 0.9,
+                span: None,
+                heading_span: None,
+                heading_style: None,
             })
             .collect(),
         warnings: Vec::new(),
         metadata: Metadata {
             generated_at: "2024-01-01T00:00:00Z".to_string(),
             tool_version: "0.1.0".to_string(),
+            ..Default::default()
         },
+        ..Default::default()
     }
 }
 
 #[test]
 fn validate_strict_fails_when_missing_required_section() {
     let note = make_note(NoteFormat::Soap, vec![("Subjective", "short")]);
-    let issues = validate::validate_note(&note, Template::Soap, true);
+    let issues = validate::validate_note(&note, &Template::Soap, true, &Config::default()).unwrap();
     assert!(issues.iter().any(|i| i.severity == Severity::Error));
 }
 
 #[test]
 fn validate_non_strict_warns_instead_of_fails() {
     let note = make_note(NoteFormat::Soap, vec![("Subjective", "short")]);
-    let issues = validate::validate_note(&note, Template::Soap, false);
+    let issues =
+        validate::validate_note(&note, &Template::Soap, false, &Config::default()).unwrap();
     assert!(!issues.iter().any(|i| i.severity == Severity::Error));
     assert!(issues.iter().any(|i| i.severity == Severity::Warn));
 }
@@ -56,9 +64,10 @@ fn preview_lists_sections() {
         &config,
         parser::ParseOptions {
             apply_heuristics: config.enable_fallback_heuristics,
+            ..Default::default()
         },
     );
-    let note = parser::build_note(candidates, NoteFormat::Soap, None, 1, Vec::new());
+    let note = parser::build_note(&input, candidates, NoteFormat::Soap, None, 1, Vec::new());
     let summary = validate::summarize_sections(&note);
     assert!(summary.iter().any(|s| s.name == "Subjective"));
     assert!(summary.iter().all(|s| s.line_count > 0));
@@ -74,9 +83,11 @@ fn convert_output_matches_baseline() {
         &config,
         parser::ParseOptions {
             apply_heuristics: config.enable_fallback_heuristics,
+            ..Default::default()
         },
     );
     let note = parser::build_note(
+        &input,
         candidates,
         NoteFormat::Soap,
         Some("tests/fixtures/soap_messy.txt".to_string()),
@@ -87,3 +98,434 @@ fn convert_output_matches_baseline() {
     let expected = fixture("tests/fixtures/soap_messy.expected.md");
     assert_eq!(output.trim_end(), expected.trim_end());
 }
+
+#[test]
+fn issues_carry_section_spans() {
+    let config = Config::default();
+    let input = "Subjective:\nshort\nAssessment: stable, no acute issues today\nAssessment: repeat\nPlan: follow up in two weeks with PCP";
+    let note = parser::parse_note(
+        input,
+        NoteFormat::Soap,
+        &config,
+        None,
+        1,
+        parser::ParseOptions {
+            apply_heuristics: true,
+            ..Default::default()
+        },
+    );
+    let issues =
+        validate::validate_note(&note, &Template::Soap, false, &Config::default()).unwrap();
+
+    let short = issues
+        .iter()
+        .find(|i| i.code == "section_too_short" && i.section.as_deref() == Some("Subjective"))
+        .unwrap();
+    let span = short.span.as_ref().unwrap();
+    assert_eq!((span.line_start, span.line_end), (1, 2));
+
+    let duplicate = issues
+        .iter()
+        .find(|i| i.code == "duplicate_section")
+        .unwrap();
+    assert_eq!(duplicate.span.as_ref().unwrap().line_start, 3);
+
+    let missing = issues
+        .iter()
+        .find(|i| i.code == "missing_required")
+        .unwrap();
+    assert_eq!(missing.section.as_deref(), Some("Objective"));
+    assert_eq!(missing.span.as_ref().unwrap().line_start, 2);
+}
+
+#[test]
+fn custom_format_uses_configured_required_sections() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.progress]
+section_order = ["Subjective", "Assessment", "Plan"]
+required_sections = ["Assessment", "Plan"]
+"#,
+    )
+    .unwrap();
+    let format: NoteFormat = "progress".parse().unwrap();
+    let note = make_note(
+        format.clone(),
+        vec![("Subjective", "Feeling better than last visit overall")],
+    );
+    let issues = validate::validate_note(&note, &format, true, &config).unwrap();
+    let missing: Vec<_> = issues
+        .iter()
+        .filter(|i| i.code == "missing_required")
+        .filter_map(|i| i.section.clone())
+        .collect();
+    assert_eq!(missing, vec!["Assessment", "Plan"]);
+    assert!(!issues.iter().any(|i| i.code == "unknown_section"));
+
+    let output = render::render_notes(&[note], OutputFormat::Md, config.csv.layout).unwrap();
+    assert!(output.contains("Format: progress"));
+}
+
+#[test]
+fn config_rules_override_builtin_validation() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[validation.soap]
+required = [{ section = "Subjective", aliases = ["S"] }, { section = "Plan", aliases = ["P"] }]
+min_lengths = { "Plan" = 5 }
+max_lengths = { "Subjective" = 30 }
+severity = { section_too_long = "error" }
+"#,
+    )
+    .unwrap();
+    let note = make_note(
+        NoteFormat::Soap,
+        vec![
+            (
+                "Subjective",
+                "Cough for three days with fevers and chills at night",
+            ),
+            ("Plan", "fluids"),
+        ],
+    );
+    let issues = validate::validate_note(&note, &NoteFormat::Soap, false, &config).unwrap();
+    assert!(!issues.iter().any(|i| i.code == "missing_required"));
+    assert!(!issues.iter().any(|i| i.code == "section_too_short"));
+    let too_long = issues
+        .iter()
+        .find(|i| i.code == "section_too_long")
+        .unwrap();
+    assert_eq!(too_long.severity, Severity::Error);
+
+    let rules = validate::RuleSet::resolve(&config, &NoteFormat::Soap).unwrap();
+    assert_eq!(rules.min_length, validate::DEFAULT_MIN_SECTION_LEN);
+    assert!(rules.describe().contains("Plan (P)"));
+}
+
+#[test]
+fn validation_for_unknown_format_is_rejected() {
+    let result = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective"]
+
+[validation.consult]
+min_length = 5
+"#,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn content_rules_report_misses_and_hits() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[[validation.soap.rules]]
+code = "plan_follow_up"
+section = "Plan"
+must_match = '(?i)follow[- ]?up\s+(in\s+)?\d+\s*(day|week|month)s?'
+message = "Plan should state a follow-up interval"
+
+[[validation.soap.rules]]
+code = "assessment_placeholder"
+section = "Assessment"
+must_not_match = '\*\*\*'
+severity = "error"
+
+[[validation.soap.rules]]
+code = "allergy_statement"
+section = "Allergies"
+any_of = ["NKDA", "penicillin"]
+"#,
+    )
+    .unwrap();
+    let mut note = make_note(
+        NoteFormat::Soap,
+        vec![
+            ("Assessment", "Viral URI *** pending review of labs"),
+            ("Plan", "Supportive care and fluids, return if worse"),
+        ],
+    );
+    note.sections[0].span = Some(clinote::models::SourceSpan {
+        line_start: 4,
+        line_end: 5,
+        byte_start: 40,
+        byte_end: 90,
+        char_start: 40,
+        char_end: 90,
+    });
+    let issues = validate::validate_note(&note, &NoteFormat::Soap, false, &config).unwrap();
+
+    let follow_up = issues.iter().find(|i| i.code == "plan_follow_up").unwrap();
+    assert_eq!(follow_up.message, "Plan should state a follow-up interval");
+    assert_eq!(follow_up.severity, Severity::Warn);
+
+    let placeholder = issues
+        .iter()
+        .find(|i| i.code == "assessment_placeholder")
+        .unwrap();
+    assert_eq!(placeholder.severity, Severity::Error);
+    assert!(placeholder.message.contains("***"));
+    assert_eq!(placeholder.span.as_ref().unwrap().line_start, 4);
+
+    assert!(!issues.iter().any(|i| i.code == "allergy_statement"));
+
+    note.sections[1].content = "Follow up in 2 weeks with PCP".to_string();
+    let issues = validate::validate_note(&note, &NoteFormat::Soap, false, &config).unwrap();
+    assert!(!issues.iter().any(|i| i.code == "plan_follow_up"));
+}
+
+#[test]
+fn invalid_content_rule_pattern_is_rejected() {
+    let result = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective"]
+
+[[validation.soap.rules]]
+code = "broken"
+section = "Plan"
+must_match = "(unclosed"
+"#,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn validation_keys_are_normalized_and_severity_codes_checked() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[validation.SOAP]
+severity = { section_too_long = "error" }
+"#,
+    )
+    .unwrap();
+    let rules = validate::RuleSet::resolve(&config, &NoteFormat::Soap).unwrap();
+    assert_eq!(
+        rules.severity.get("section_too_long"),
+        Some(&Severity::Error)
+    );
+
+    let err = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[validation.soap]
+severity = { section_to_long = "error" }
+"#,
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Unknown issue code 'section_to_long'"));
+
+    let custom = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[validation.soap]
+severity = { plan_follow_up = "info" }
+
+[[validation.soap.rules]]
+code = "plan_follow_up"
+section = "Plan"
+must_match = "follow"
+"#,
+    )
+    .unwrap();
+    assert!(custom.validation.contains_key("soap"));
+}
+
+#[test]
+fn allergies_need_an_explicit_status_but_not_length() {
+    let parse = |text: &str| {
+        parser::parse_note(
+            text,
+            NoteFormat::Hp,
+            &Config::default(),
+            None,
+            1,
+            parser::ParseOptions::default(),
+        )
+    };
+    let short = parse("Chief Complaint: cough\nAllergies: NKDA");
+    let issues = validate::validate_note(&short, &Template::Hp, false, &Config::default()).unwrap();
+    assert!(!issues
+        .iter()
+        .any(|i| i.code == "section_too_short" && i.section.as_deref() == Some("Allergies")));
+    assert!(!issues.iter().any(|i| i.code == "allergy_status_missing"));
+
+    for vague in [
+        "Allergies: patient is not sure about past reactions",
+        "Allergies: not reviewed",
+    ] {
+        let issues =
+            validate::validate_note(&parse(vague), &Template::Hp, false, &Config::default())
+                .unwrap();
+        let issue = issues
+            .iter()
+            .find(|i| i.code == "allergy_status_missing")
+            .expect("missing status flagged");
+        assert_eq!(issue.severity, Severity::Warn);
+    }
+    let issues = validate::validate_note(
+        &parse("Allergies: not reviewed"),
+        &Template::Hp,
+        false,
+        &Config::default(),
+    )
+    .unwrap();
+    assert!(issues
+        .iter()
+        .any(|i| i.code == "section_too_short" && i.section.as_deref() == Some("Allergies")));
+}
+
+#[test]
+fn implausible_vitals_are_flagged() {
+    let parse = |text: &str| {
+        parser::parse_note(
+            text,
+            NoteFormat::Hp,
+            &Config::default(),
+            None,
+            1,
+            parser::ParseOptions::default(),
+        )
+    };
+    let note = parse("Physical Exam: BP 120/80, HR 900, temp 98.6 F, SpO2 97%");
+    let issues = validate::validate_note(&note, &Template::Hp, false, &Config::default()).unwrap();
+    let vitals: Vec<_> = issues
+        .iter()
+        .filter(|i| i.code == "implausible_vital")
+        .collect();
+    assert_eq!(vitals.len(), 1);
+    assert!(vitals[0].message.contains("Heart rate 900"));
+    assert_eq!(vitals[0].severity, Severity::Warn);
+
+    let repeated = parse("Physical Exam: HR 88, BP 120/80\nRepeat vitals: P 900, BP 118/76");
+    assert_eq!(repeated.vitals.as_ref().unwrap().heart_rate, Some(88));
+    let issues =
+        validate::validate_note(&repeated, &Template::Hp, false, &Config::default()).unwrap();
+    assert!(issues
+        .iter()
+        .any(|i| i.code == "implausible_vital" && i.message.contains("Heart rate 900")));
+
+    let exam = parse("Physical Exam: tender at T12, P 2 mm pupils, R 3 cm laceration");
+    assert_eq!(exam.vitals, None);
+    let issues = validate::validate_note(&exam, &Template::Hp, false, &Config::default()).unwrap();
+    assert!(!issues.iter().any(|i| i.code == "implausible_vital"));
+}
+
+#[test]
+fn hp_ros_must_cover_enough_systems() {
+    let parse = |content: &str, config: &Config| {
+        parser::parse_note(
+            &format!("ROS: {}", content),
+            NoteFormat::Hp,
+            config,
+            None,
+            1,
+            parser::ParseOptions::default(),
+        )
+    };
+    let config = Config::default();
+    let sparse = parse("denies fever or chills", &config);
+    let issues =
+        validate::validate_note(&sparse, &Template::Hp, false, &Config::default()).unwrap();
+    let issue = issues
+        .iter()
+        .find(|i| i.code == "ros_incomplete")
+        .expect("sparse ROS flagged");
+    assert_eq!(issue.severity, Severity::Warn);
+    assert!(issue.message.contains("covers 1 of at least 2"));
+
+    let covered = parse("denies fever. Reports cough, no chest pain.", &config);
+    let issues =
+        validate::validate_note(&covered, &Template::Hp, false, &Config::default()).unwrap();
+    assert!(!issues.iter().any(|i| i.code == "ros_incomplete"));
+
+    let otherwise = parse("Resp: cough, otherwise negative", &config);
+    let issues =
+        validate::validate_note(&otherwise, &Template::Hp, false, &Config::default()).unwrap();
+    assert!(!issues.iter().any(|i| i.code == "ros_incomplete"));
+
+    let config = Config::from_toml_str(
+        r#"
+[formats.hp]
+section_order = ["Chief Complaint", "HPI", "ROS", "Physical Exam", "Assessment", "Plan"]
+
+[validation.hp]
+ros_min_systems = 4
+"#,
+    )
+    .unwrap();
+    let issues = validate::validate_note(&covered, &Template::Hp, false, &config).unwrap();
+    assert!(issues.iter().any(|i| i.code == "ros_incomplete"));
+}
+
+#[test]
+fn content_rule_spans_point_at_the_match() {
+    let config = Config::from_toml_str(
+        r#"
+[formats.soap]
+section_order = ["Subjective", "Objective", "Assessment", "Plan"]
+
+[[validation.soap.rules]]
+code = "assessment_placeholder"
+section = "Assessment"
+none_of = ["TBD"]
+"#,
+    )
+    .unwrap();
+    let text = "Subjective:\nCough for three days.\nObjective:\nLungs clear bilaterally.\nAssessment:\nViral URI likely.\nRule out pneumonia TBD.\nPlan:\nFluids and rest.\n";
+    let pipeline = clinote::pipeline::Pipeline::new(
+        config.clone(),
+        clinote::pipeline::PipelineOptions::from_config(&config, NoteFormat::Soap),
+    );
+    let output = pipeline.parse_str(text, None).unwrap();
+    let issue = output
+        .issues()
+        .find(|i| i.code == "assessment_placeholder")
+        .unwrap();
+    let span = issue.span.as_ref().unwrap();
+    assert_eq!((span.line_start, span.line_end), (7, 7));
+}
+
+#[test]
+fn invalid_content_rule_built_in_code_is_an_error() {
+    let mut config = Config::default();
+    config.validation.insert(
+        "soap".to_string(),
+        clinote::config::ValidationSpec {
+            rules: vec![clinote::config::ContentRule {
+                code: "broken".to_string(),
+                section: "Plan".to_string(),
+                message: None,
+                severity: Severity::Warn,
+                must_match: Some("(unclosed".to_string()),
+                must_not_match: None,
+                any_of: Vec::new(),
+                none_of: Vec::new(),
+            }],
+            ..Default::default()
+        },
+    );
+    assert!(validate::RuleSet::resolve(&config, &NoteFormat::Soap).is_err());
+    let note = make_note(NoteFormat::Soap, vec![("Plan", "Fluids and rest")]);
+    assert!(validate::validate_note(&note, &Template::Soap, false, &config).is_err());
+    let options = clinote::pipeline::PipelineOptions::from_config(&config, NoteFormat::Soap);
+    let result =
+        clinote::pipeline::Pipeline::new(config, options).parse_str("Plan: Fluids and rest", None);
+    assert!(result.is_err());
+}